@@ -0,0 +1,107 @@
+//! `#[derive(OrcRecord)]` for the `orc-format` crate. Use it through the `derive` feature of
+//! `orc-format`, which re-exports the macro as `orc_format::record::OrcRecord`.
+//!
+//! The generated `schema()` builds the schema anew on each call. It cannot be an associated
+//! const, since `Schema` holds `String`s, `Vec`s and `Box`es, which a const cannot allocate, and
+//! a cached static would not work for generic structs, whose instantiations would all share it.
+//! The schema is only needed once, to create a writer, while rows are written by `write_to`
+//! without it.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Result};
+
+/// Implements `OrcRecord` and `OrcField` for a struct with named fields. Every field type must
+/// implement `OrcField`. A field can be given a different ORC name with `#[orc(rename = "...")]`.
+#[proc_macro_derive(OrcRecord, attributes(orc))]
+pub fn derive_orc_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input.ident, "OrcRecord requires a struct with named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "OrcRecord can only be derived for structs")),
+    };
+
+    let mut schema_fields: Vec<TokenStream2> = Vec::new();
+    let mut writes: Vec<TokenStream2> = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let name = match field_rename(field)? {
+            Some(name) => name,
+            None => ident.to_string().trim_start_matches("r#").to_owned(),
+        };
+        schema_fields.push(quote! {
+            ::orc_format::schema::Field(
+                #name.to_owned(),
+                <#ty as ::orc_format::record::OrcField>::schema(),
+            )
+        });
+        writes.push(quote! {
+            ::orc_format::record::OrcField::write_field(&self.#ident, data.child(#i));
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::orc_format::record::OrcRecord for #name #ty_generics #where_clause {
+            fn schema() -> ::orc_format::schema::Schema {
+                ::orc_format::schema::Schema::Struct(vec![#(#schema_fields),*])
+            }
+
+            fn write_to(&self, data: &mut ::orc_format::writer::data::StructData) {
+                data.write();
+                #(#writes)*
+            }
+        }
+
+        impl #impl_generics ::orc_format::record::OrcField for #name #ty_generics #where_clause {
+            fn schema() -> ::orc_format::schema::Schema {
+                <Self as ::orc_format::record::OrcRecord>::schema()
+            }
+
+            fn write_field(&self, data: &mut ::orc_format::writer::data::Data) {
+                ::orc_format::record::OrcRecord::write_to(self, data.unwrap_struct());
+            }
+        }
+    })
+}
+
+/// Parses `#[orc(rename = "...")]`, the only supported field attribute.
+fn field_rename(field: &syn::Field) -> Result<Option<String>> {
+    let mut rename: Option<String> = None;
+    for attr in &field.attrs {
+        if !attr.path.is_ident("orc") {
+            continue;
+        }
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[orc(rename = \"...\")]")),
+        };
+        for nested in &list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    match &nv.lit {
+                        Lit::Str(s) => rename = Some(s.value()),
+                        lit => return Err(Error::new_spanned(lit, "expected a string literal")),
+                    }
+                }
+                _ => return Err(Error::new_spanned(nested, "unknown orc attribute")),
+            }
+        }
+    }
+    Ok(rename)
+}
@@ -0,0 +1,102 @@
+use std::io::Cursor;
+
+use orc_format::reader::Reader;
+use orc_format::record::{OrcField, OrcRecord};
+use orc_format::schema::{Field, Schema};
+use orc_format::value::Value;
+use orc_format::writer::{Config, Writer};
+
+#[derive(OrcRecord)]
+struct Address {
+    city: String,
+    zip: Option<i32>,
+}
+
+#[derive(OrcRecord)]
+struct User {
+    id: i64,
+    #[orc(rename = "user_name")]
+    name: String,
+    active: bool,
+    score: Option<f64>,
+    tags: Vec<String>,
+    avatar: Vec<u8>,
+    address: Address,
+    previous: Vec<Option<Address>>,
+}
+
+#[test]
+fn test_schema() {
+    let address = Schema::Struct(vec![
+        Field("city".to_owned(), Schema::String),
+        Field("zip".to_owned(), Schema::Int),
+    ]);
+    assert_eq!(<User as OrcRecord>::schema(), Schema::Struct(vec![
+        Field("id".to_owned(), Schema::Long),
+        Field("user_name".to_owned(), Schema::String),
+        Field("active".to_owned(), Schema::Boolean),
+        Field("score".to_owned(), Schema::Double),
+        Field("tags".to_owned(), Schema::List(Box::new(Schema::String))),
+        Field("avatar".to_owned(), Schema::Binary),
+        Field("address".to_owned(), address.clone()),
+        Field("previous".to_owned(), Schema::List(Box::new(address))),
+    ]));
+    assert_eq!(<Address as OrcField>::schema(), <Address as OrcRecord>::schema());
+}
+
+#[test]
+fn test_write_records() {
+    let users = vec![
+        User {
+            id: 1,
+            name: "alice".to_owned(),
+            active: true,
+            score: Some(2.5),
+            tags: vec!["a".to_owned(), "b".to_owned()],
+            avatar: vec![1, 2, 3],
+            address: Address { city: "Oslo".to_owned(), zip: Some(150) },
+            previous: vec![None, Some(Address { city: "Bergen".to_owned(), zip: None })],
+        },
+        User {
+            id: 2,
+            name: "bob".to_owned(),
+            active: false,
+            score: None,
+            tags: vec![],
+            avatar: vec![],
+            address: Address { city: "Rome".to_owned(), zip: None },
+            previous: vec![],
+        },
+    ];
+    let schema = <User as OrcRecord>::schema();
+    let mut writer = Writer::new(Vec::new(), &schema, Config::new()).unwrap();
+    for user in &users {
+        writer.write_record(user).unwrap();
+    }
+    let out = writer.finish().unwrap();
+
+    let mut reader = Reader::new(Cursor::new(out)).unwrap();
+    let s = |x: &str| Value::String(x.to_owned());
+    assert_eq!(reader.rows().unwrap(), vec![
+        Value::Struct(vec![
+            Value::Int(1),
+            s("alice"),
+            Value::Bool(true),
+            Value::Double(2.5),
+            Value::List(vec![s("a"), s("b")]),
+            Value::Binary(vec![1, 2, 3]),
+            Value::Struct(vec![s("Oslo"), Value::Int(150)]),
+            Value::List(vec![Value::Null, Value::Struct(vec![s("Bergen"), Value::Null])]),
+        ]),
+        Value::Struct(vec![
+            Value::Int(2),
+            s("bob"),
+            Value::Bool(false),
+            Value::Null,
+            Value::List(vec![]),
+            Value::Binary(vec![]),
+            Value::Struct(vec![s("Rome"), Value::Null]),
+            Value::List(vec![]),
+        ]),
+    ]);
+}
@@ -0,0 +1,33 @@
+//! Salvages the intact stripes of a damaged ORC file into a new file.
+//!
+//! Usage: orc_salvage <type> <damaged.orc> <output.orc>
+//!
+//! where <type> is the file's schema as an ORC type description, e.g. `struct<a:bigint,b:string>`.
+
+use orc_format::recovery::Salvage;
+use orc_format::schema::Schema;
+use orc_format::writer::{Config, Writer};
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Result};
+use std::process;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        eprintln!("Usage: {} <type> <damaged.orc> <output.orc>", args[0]);
+        process::exit(2);
+    }
+    let schema: Schema = args[1].parse()?;
+    let input = File::open(&args[2])?;
+    let input_len = input.metadata()?.len();
+    let mut salvage = Salvage::scan(BufReader::new(input), &schema)?;
+
+    let config = Config::new().with_compression(salvage.compression());
+    let mut writer = Writer::new(BufWriter::new(File::create(&args[3])?), &schema, config)?;
+    salvage.write_to(&mut writer)?;
+    writer.finish()?;
+    println!("Salvaged {} rows in {} stripes ({} of {} bytes)",
+        salvage.num_rows(), salvage.num_stripes(), salvage.salvaged_length(), input_len);
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Writes a copy of an ORC file with only some of its fields, without decoding them.
+//!
+//! Usage: orc_select [--drop] <field,...> <input.orc> <output.orc>
+//!
+//! With `--drop`, the listed fields are removed and all others kept. A field of a struct column is 
+//! listed by its dotted path, e.g. `user.email`; the elements of a list, map or union go with it.
+
+use orc_format::projection::{drop_fields, select_fields};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Result};
+use std::process;
+
+fn main() -> Result<()> {
+    let mut args: Vec<String> = env::args().collect();
+    let drop = args.len() > 1 && args[1] == "--drop";
+    if drop {
+        args.remove(1);
+    }
+    if args.len() != 4 {
+        eprintln!("Usage: {} [--drop] <field[.nested field],...> <input.orc> <output.orc>", args[0]);
+        process::exit(2);
+    }
+    let fields: Vec<&str> = args[1].split(',').map(str::trim).collect();
+    let input = BufReader::new(File::open(&args[2])?);
+    let output = BufWriter::new(File::create(&args[3])?);
+    let result = if drop { drop_fields(input, output, &fields) } else { select_fields(input, output, &fields) };
+    if let Err(err) = result.and_then(|output| output.into_inner().map_err(|err| err.into_error())) {
+        fs::remove_file(&args[3])?;
+        return Err(err);
+    }
+    println!("{} fields {}", if drop { "Dropped" } else { "Kept" }, fields.join(","));
+    Ok(())
+}
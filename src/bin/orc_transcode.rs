@@ -0,0 +1,71 @@
+//! Rewrites an ORC file with a different compression, stripe size or row index stride.
+//!
+//! Usage: orc_transcode [--compression none|snappy|zstd] [--block-size <bytes>] [--stripe-size <bytes>] 
+//!     [--row-index-stride <rows>] <input.orc> <output.orc>
+//!
+//! The output is compressed with zstd, has 128 MiB stripes and a row index entry every 10000 rows 
+//! unless specified otherwise. Only inputs using the DIRECT encoding with RLEv1 and NONE, Snappy or 
+//! Zstd compression can be read; legacy Hive or ORC Java files with RLEv2 or dictionary encodings 
+//! or ZLIB compression are rejected with an error naming the unsupported compression, or the 
+//! encoding and its column.
+
+use orc_format::reader::Reader;
+use orc_format::transcode::transcode;
+use orc_format::writer::compression::{NoCompression, SnappyCompression, ZstdCompression};
+use orc_format::writer::{Config, Writer};
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, Result};
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {} [--compression none|snappy|zstd] [--block-size <bytes>] [--stripe-size <bytes>] \
+        [--row-index-stride <rows>] <input.orc> <output.orc>", program);
+    process::exit(2);
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let mut compression = "zstd".to_owned();
+    let mut block_size: Option<usize> = None;
+    let mut stripe_size: usize = 128 << 20;
+    let mut row_index_stride: u32 = 10000;
+    let mut paths: Vec<&str> = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        let value = || args.get(i + 1).cloned().unwrap_or_else(|| usage(&args[0]));
+        let number = || value().parse::<usize>().unwrap_or_else(|_| usage(&args[0]));
+        match args[i].as_str() {
+            "--compression" => compression = value(),
+            "--block-size" => block_size = Some(number()),
+            "--stripe-size" => stripe_size = number(),
+            "--row-index-stride" => row_index_stride = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            path if !path.starts_with("--") => {
+                paths.push(path);
+                i += 1;
+                continue;
+            }
+            _ => usage(&args[0]),
+        }
+        i += 2;
+    }
+    if paths.len() != 2 {
+        usage(&args[0]);
+    }
+
+    let compression = match (compression.as_str(), block_size) {
+        ("none", _) => NoCompression::new().build(),
+        ("snappy", Some(n)) => SnappyCompression::new().with_block_size(n).build(),
+        ("snappy", None) => SnappyCompression::new().build(),
+        ("zstd", Some(n)) => ZstdCompression::new().with_block_size(n).build(),
+        ("zstd", None) => ZstdCompression::new().build(),
+        _ => usage(&args[0]),
+    };
+    let config = Config::new().with_compression(compression).with_stripe_size(stripe_size)
+        .with_row_index_stride(row_index_stride);
+    let schema = Reader::new(BufReader::new(File::open(paths[0])?))?.schema().clone();
+    let writer = Writer::create(paths[1], &schema, config)?;
+    let (num_rows, _) = transcode(BufReader::new(File::open(paths[0])?), writer)?;
+    println!("Transcoded {} rows", num_rows);
+    Ok(())
+}
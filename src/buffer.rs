@@ -1,28 +1,60 @@
 use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use crate::memory::MemoryAccount;
 
 /// Simple wrapper around a Vec<u8>, which we use for all our substantial memory allocations.
-/// The encapsulation here should make it easier to later swap in a different approach to 
-/// allocation if we want to.
+/// When the buffer belongs to a writer, its storage is drawn from (and returned to) the writer's
+/// `MemoryAccount`, so that the writer's memory usage can be tracked and limited.
 pub struct Buffer {
     data: Vec<u8>,
+    memory: Option<Arc<MemoryAccount>>,
 }
 
 impl Buffer {
-    pub fn new() -> Buffer {
-        Self::with_capacity(0)
+    pub fn new(memory: Option<&Arc<MemoryAccount>>) -> Buffer {
+        Self::with_capacity(0, memory)
     }
 
-    pub fn with_capacity(capacity: usize) -> Buffer {
+    pub fn with_capacity(capacity: usize, memory: Option<&Arc<MemoryAccount>>) -> Buffer {
+        let data = match memory {
+            Some(m) if capacity > 0 => m.allocate(capacity),
+            _ => Vec::with_capacity(capacity),
+        };
         Buffer {
-            data: Vec::with_capacity(capacity)
+            data,
+            memory: memory.cloned(),
         }
     }
 
-    pub fn capacity(&self) -> usize {
-        self.data.capacity()
+    /// Ensures there is room for at least `additional` more bytes without further allocation.
+    #[inline(always)]
+    fn reserve(&mut self, additional: usize) {
+        if self.data.len() + additional > self.data.capacity() {
+            self.grow(self.data.len() + additional);
+        }
+    }
+
+    #[inline(never)]
+    fn grow(&mut self, min_capacity: usize) {
+        match &self.memory {
+            Some(m) => {
+                let new_capacity = min_capacity.max(2 * self.data.capacity()).max(64);
+                let mut data = m.allocate(new_capacity);
+                data.extend_from_slice(&self.data);
+                let old = std::mem::replace(&mut self.data, data);
+                if old.capacity() > 0 {
+                    m.release(old);
+                }
+            }
+            None => self.data.reserve(min_capacity - self.data.len()),
+        }
     }
 
     pub fn resize(&mut self, new_len: usize) {
+        if new_len > self.data.len() {
+            self.reserve(new_len - self.data.len());
+        }
         self.data.resize(new_len, 0);
     }
 
@@ -32,12 +64,37 @@ impl Buffer {
         }
     }
 
+    /// Empties the buffer. The storage is kept for reuse, unless the owning writer is over its
+    /// memory limit or has been asked to release memory, in which case it is released.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        if let Some(m) = &self.memory {
+            if m.should_release() && self.data.capacity() > 0 {
+                m.release(std::mem::take(&mut self.data));
+            }
+        }
+    }
+
+    #[inline(always)]
     pub fn write_u8(&mut self, b: u8) {
+        self.reserve(1);
         self.data.push(b);
     }
 
+    #[inline(always)]
     pub fn write_bytes(&mut self, bytes: &[u8]) {
-        self.data.extend(bytes);
+        self.reserve(bytes.len());
+        self.data.extend_from_slice(bytes);
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if let Some(m) = &self.memory {
+            if self.data.capacity() > 0 {
+                m.release(std::mem::take(&mut self.data));
+            }
+        }
     }
 }
 
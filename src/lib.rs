@@ -1,8 +1,14 @@
 
 mod buffer;
 mod protos;
+pub mod memory;
+pub mod projection;
 pub mod reader;
+pub mod record;
+pub mod recovery;
 pub mod schema;
+pub mod transcode;
+pub mod value;
 pub mod writer;
 
 // #[cfg(test)]
@@ -0,0 +1,362 @@
+//! Control over the memory used by a `Writer` for buffering stripes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// A source of the byte buffers in which a writer accumulates encoded (and compressed) stream data.
+/// Buffers are requested when a stream needs to grow, and handed back when the stream no longer
+/// needs them.
+pub trait Allocator: Send + Sync {
+    /// Returns an empty vector with a capacity of at least `capacity`.
+    fn allocate(&self, capacity: usize) -> Vec<u8>;
+
+    /// Takes back a vector obtained from `allocate`, which is no longer in use.
+    fn release(&self, buf: Vec<u8>);
+}
+
+/// Allocates directly from the global allocator. This is the default.
+#[derive(Default)]
+pub struct SystemAllocator;
+
+impl Allocator for SystemAllocator {
+    fn allocate(&self, capacity: usize) -> Vec<u8> {
+        Vec::with_capacity(capacity)
+    }
+
+    fn release(&self, _buf: Vec<u8>) {}
+}
+
+/// Keeps released buffers (up to a total capacity of `max_pooled_bytes`) to satisfy later
+/// requests, so that writers which repeatedly grow and release streams (e.g. one per partition)
+/// can share allocations. A single pool can be shared by many writers.
+pub struct BufferPool {
+    max_pooled_bytes: usize,
+    pool: Mutex<PoolState>,
+}
+
+struct PoolState {
+    buffers: Vec<Vec<u8>>,
+    pooled_bytes: usize,
+}
+
+impl BufferPool {
+    pub fn new(max_pooled_bytes: usize) -> Self {
+        BufferPool {
+            max_pooled_bytes,
+            pool: Mutex::new(PoolState {
+                buffers: Vec::new(),
+                pooled_bytes: 0,
+            }),
+        }
+    }
+
+    /// Total capacity of the buffers currently held in the pool.
+    pub fn pooled_bytes(&self) -> usize {
+        self.pool.lock().unwrap().pooled_bytes
+    }
+}
+
+impl Allocator for BufferPool {
+    fn allocate(&self, capacity: usize) -> Vec<u8> {
+        let mut pool = self.pool.lock().unwrap();
+        // Use the smallest pooled buffer that is large enough.
+        let best = pool.buffers.iter()
+            .enumerate()
+            .filter(|(_, b)| b.capacity() >= capacity)
+            .min_by_key(|(_, b)| b.capacity())
+            .map(|(i, _)| i);
+        match best {
+            Some(i) => {
+                let buf = pool.buffers.swap_remove(i);
+                pool.pooled_bytes -= buf.capacity();
+                buf
+            }
+            None => Vec::with_capacity(capacity),
+        }
+    }
+
+    fn release(&self, mut buf: Vec<u8>) {
+        let mut pool = self.pool.lock().unwrap();
+        if pool.pooled_bytes + buf.capacity() <= self.max_pooled_bytes {
+            buf.clear();
+            pool.pooled_bytes += buf.capacity();
+            pool.buffers.push(buf);
+        }
+    }
+}
+
+/// Tracks the buffer memory held by a single writer, drawing the buffers from its `Allocator`.
+pub(crate) struct MemoryAccount {
+    allocator: Arc<dyn Allocator>,
+    used: AtomicUsize,
+    limit: Option<usize>,
+    releasing: AtomicBool,
+}
+
+impl MemoryAccount {
+    pub fn new(allocator: Arc<dyn Allocator>, limit: Option<usize>) -> Self {
+        MemoryAccount {
+            allocator,
+            used: AtomicUsize::new(0),
+            limit,
+            releasing: AtomicBool::new(false),
+        }
+    }
+
+    pub fn allocate(&self, capacity: usize) -> Vec<u8> {
+        let buf = self.allocator.allocate(capacity);
+        self.used.fetch_add(buf.capacity(), Ordering::Relaxed);
+        buf
+    }
+
+    pub fn release(&self, buf: Vec<u8>) {
+        self.used.fetch_sub(buf.capacity(), Ordering::Relaxed);
+        self.allocator.release(buf);
+    }
+
+    /// Number of bytes of buffer capacity currently held.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub fn is_over_limit(&self) -> bool {
+        match self.limit {
+            Some(limit) => self.used() >= limit,
+            None => false,
+        }
+    }
+
+    /// While set, buffers give up their storage when cleared instead of keeping it for reuse.
+    pub fn set_releasing(&self, releasing: bool) {
+        self.releasing.store(releasing, Ordering::Relaxed);
+    }
+
+    /// Whether cleared buffers should release their storage.
+    pub fn should_release(&self) -> bool {
+        self.releasing.load(Ordering::Relaxed) || self.is_over_limit()
+    }
+}
+
+/// Coordinates the memory use of many writers, in the manner of ORC Java's `MemoryManager`. Each
+/// writer registers (via `Config::with_memory_manager`) with its configured stripe size. When the
+/// stripe sizes of all registered writers add up to more than the pool, each writer's effective
+/// stripe size is scaled down proportionally. In addition, when the buffer memory held by the
+/// writers exceeds the pool, the largest writers are asked to flush early and release their buffers.
+///
+/// Writers report to the manager periodically (every `ROWS_BETWEEN_CHECKS` rows), so the pool may
+/// be briefly exceeded.
+pub struct MemoryManager {
+    pool_size: usize,
+    state: Mutex<ManagerState>,
+}
+
+struct ManagerState {
+    writers: HashMap<u64, WriterEntry>,
+    next_id: u64,
+    total_requested: usize,
+    total_current: usize,
+}
+
+struct WriterEntry {
+    requested: usize,
+    current: usize,
+    flush_requested: bool,
+}
+
+impl MemoryManager {
+    /// Number of rows a writer writes between reports of its size to the manager.
+    pub const ROWS_BETWEEN_CHECKS: u64 = 5000;
+
+    pub fn new(pool_size: usize) -> Self {
+        MemoryManager {
+            pool_size,
+            state: Mutex::new(ManagerState {
+                writers: HashMap::new(),
+                next_id: 0,
+                total_requested: 0,
+                total_current: 0,
+            }),
+        }
+    }
+
+    pub fn pool_size(&self) -> usize {
+        self.pool_size
+    }
+
+    pub fn num_writers(&self) -> usize {
+        self.state.lock().unwrap().writers.len()
+    }
+
+    /// The factor (at most 1) by which the stripe sizes of registered writers are scaled.
+    pub fn allocation_scale(&self) -> f64 {
+        self.state.lock().unwrap().scale(self.pool_size)
+    }
+
+    /// Total buffer memory held by registered writers, as of their last reports.
+    pub fn current_usage(&self) -> usize {
+        self.state.lock().unwrap().total_current
+    }
+
+    pub(crate) fn register(self: &Arc<Self>, stripe_size: usize) -> MemoryRegistration {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.total_requested += stripe_size;
+        state.writers.insert(id, WriterEntry {
+            requested: stripe_size,
+            current: 0,
+            flush_requested: false,
+        });
+        MemoryRegistration {
+            manager: self.clone(),
+            id,
+        }
+    }
+}
+
+impl ManagerState {
+    fn scale(&self, pool_size: usize) -> f64 {
+        if self.total_requested <= pool_size {
+            1.0
+        } else {
+            pool_size as f64 / self.total_requested as f64
+        }
+    }
+
+    fn set_current(&mut self, id: u64, current: usize) {
+        let entry = self.writers.get_mut(&id).unwrap();
+        self.total_current = self.total_current - entry.current + current;
+        entry.current = current;
+    }
+}
+
+/// A writer's registration with a `MemoryManager`, which is removed when dropped.
+pub(crate) struct MemoryRegistration {
+    manager: Arc<MemoryManager>,
+    id: u64,
+}
+
+/// What a writer should do after reporting to its `MemoryManager`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum MemoryCheck {
+    Continue,
+    /// The stripe has reached the writer's effective stripe size and should be flushed.
+    FlushStripe,
+    /// The pool is under pressure: the stripe should be flushed and the writer's buffers released.
+    FlushAndRelease,
+}
+
+impl MemoryRegistration {
+    /// Reports the writer's current stripe size and the buffer memory it holds.
+    pub fn check(&self, stripe_size: usize, memory_used: usize) -> MemoryCheck {
+        let pool_size = self.manager.pool_size;
+        let mut state = self.manager.state.lock().unwrap();
+        state.set_current(self.id, memory_used);
+        let scale = state.scale(pool_size);
+        let entry = state.writers.get_mut(&self.id).unwrap();
+        if entry.flush_requested {
+            entry.flush_requested = false;
+            return MemoryCheck::FlushAndRelease;
+        }
+        if stripe_size as f64 >= entry.requested as f64 * scale {
+            return MemoryCheck::FlushStripe;
+        }
+
+        // Under pressure, ask the largest writers to flush until the rest fit in the pool.
+        let mut excess = state.total_current.saturating_sub(pool_size);
+        if excess == 0 {
+            return MemoryCheck::Continue;
+        }
+        let mut candidates: Vec<(usize, u64)> = state.writers.iter()
+            .filter(|(_, w)| !w.flush_requested && w.current > 0)
+            .map(|(&id, w)| (w.current, id))
+            .collect();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+        let mut flush_self = false;
+        for (size, id) in candidates {
+            if excess == 0 {
+                break;
+            }
+            if id == self.id {
+                flush_self = true;
+            } else {
+                state.writers.get_mut(&id).unwrap().flush_requested = true;
+            }
+            excess = excess.saturating_sub(size);
+        }
+        if flush_self { MemoryCheck::FlushAndRelease } else { MemoryCheck::Continue }
+    }
+
+    /// Reports the buffer memory held by the writer, e.g. after a flush.
+    pub fn report(&self, memory_used: usize) {
+        self.manager.state.lock().unwrap().set_current(self.id, memory_used);
+    }
+}
+
+impl Drop for MemoryRegistration {
+    fn drop(&mut self) {
+        let mut state = self.manager.state.lock().unwrap();
+        state.set_current(self.id, 0);
+        let entry = state.writers.remove(&self.id).unwrap();
+        state.total_requested -= entry.requested;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_pool() {
+        let pool = Arc::new(BufferPool::new(1000));
+        let account = MemoryAccount::new(pool.clone(), None);
+        let a = account.allocate(100);
+        let b = account.allocate(600);
+        assert_eq!(account.used(), a.capacity() + b.capacity());
+        account.release(a);
+        account.release(b);
+        assert_eq!(account.used(), 0);
+        assert_eq!(pool.pooled_bytes(), 700);
+
+        // The smallest sufficient buffer is reused; a request no pooled buffer can satisfy is not.
+        let c = account.allocate(50);
+        assert_eq!(c.capacity(), 100);
+        let d = account.allocate(5000);
+        assert_eq!(pool.pooled_bytes(), 600);
+        account.release(d);
+        assert_eq!(pool.pooled_bytes(), 600);
+    }
+
+    #[test]
+    fn test_memory_manager() {
+        let manager = Arc::new(MemoryManager::new(1000));
+        let a = manager.register(400);
+        let b = manager.register(400);
+        assert_eq!(manager.allocation_scale(), 1.0);
+
+        // Over the pool: the largest writer is asked to flush, and is told so on its next check.
+        assert_eq!(a.check(100, 700), MemoryCheck::Continue);
+        assert_eq!(b.check(100, 500), MemoryCheck::Continue);
+        assert_eq!(manager.current_usage(), 1200);
+        assert_eq!(a.check(100, 700), MemoryCheck::FlushAndRelease);
+        a.report(0);
+        assert_eq!(manager.current_usage(), 500);
+        assert_eq!(b.check(100, 900), MemoryCheck::Continue);
+        assert_eq!(b.check(100, 1100), MemoryCheck::FlushAndRelease);
+        b.report(0);
+
+        // Once the requested stripe sizes exceed the pool, they are scaled down.
+        let c = manager.register(1200);
+        assert_eq!(manager.num_writers(), 3);
+        assert_eq!(manager.allocation_scale(), 0.5);
+        assert_eq!(a.check(150, 0), MemoryCheck::Continue);
+        assert_eq!(a.check(200, 0), MemoryCheck::FlushStripe);
+        assert_eq!(c.check(599, 0), MemoryCheck::Continue);
+        assert_eq!(c.check(600, 0), MemoryCheck::FlushStripe);
+
+        drop(c);
+        assert_eq!(manager.num_writers(), 2);
+        assert_eq!(manager.allocation_scale(), 1.0);
+    }
+}
@@ -0,0 +1,296 @@
+//! Removing columns from ORC files without decoding them.
+
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use protobuf::RepeatedField;
+
+use crate::protos::orc_proto;
+use crate::reader::metadata::{FileTail, invalid_data};
+use crate::reader::read_stripe_footer;
+use crate::schema::{Field, Schema};
+use crate::writer::compression::{Compression, NoCompression, SnappyCompression, ZstdCompression};
+use crate::writer::{Config, Writer};
+
+/// Writes a copy of the ORC file `input` to `output` that only has the given fields, in their 
+/// original order. A field of a struct column is named by its dotted path, e.g. `user.email`, and 
+/// keeps the struct column with only the selected fields. The streams of the kept columns are 
+/// copied as they are, and column ids are renumbered in the type tree, stream lists, encodings and 
+/// statistics. User metadata is kept. Fields can only be named through structs; the elements of a 
+/// list, map or union are kept or dropped along with it.
+pub fn select_fields<R: Read + Seek, W: Write>(input: R, output: W, fields: &[&str]) -> Result<W> {
+    project(input, output, fields, false)
+}
+
+/// Writes a copy of the ORC file `input` to `output` without the given fields, which are named as 
+/// in [`select_fields`].
+pub fn drop_fields<R: Read + Seek, W: Write>(input: R, output: W, fields: &[&str]) -> Result<W> {
+    project(input, output, fields, true)
+}
+
+fn project<R: Read + Seek, W: Write>(mut input: R, output: W, fields: &[&str], drop: bool) -> Result<W> {
+    let file_len = input.seek(SeekFrom::End(0))?;
+    let tail = FileTail::read(&mut input, file_len)?;
+    let schema_fields = match tail.schema()? {
+        Schema::Struct(fields) => fields,
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "Only fields of a struct can be selected")),
+    };
+    let paths: Vec<Vec<&str>> = fields.iter().map(|field| field.split('.').collect()).collect();
+    for (field, path) in fields.iter().zip(&paths) {
+        let mut fields = &schema_fields;
+        for (i, name) in path.iter().enumerate() {
+            let schema = fields.iter().find(|Field(n, _)| n == name).map(|Field(_, schema)| schema);
+            match schema {
+                Some(Schema::Struct(children)) => fields = children,
+                Some(_) if i + 1 == path.len() => {}
+                _ => return Err(Error::new(ErrorKind::InvalidInput, format!("No field named {:?}", field))),
+            }
+        }
+    }
+
+    // Pruning whole subtrees keeps the columns in pre-order, so the kept columns are renumbered in 
+    // their original order.
+    let mut kept = vec![true];
+    let paths: Vec<&[&str]> = paths.iter().map(Vec::as_slice).collect();
+    let kept_fields = prune_fields(&schema_fields, &paths, drop, &mut kept);
+    let types = tail.footer.get_types();
+    if kept.len() != types.len() {
+        return Err(invalid_data("Schema does not match the number of types"));
+    }
+    let mut next_id = 0;
+    let new_ids: Vec<Option<u32>> = kept.iter().map(|&keep| keep.then(|| {
+        next_id += 1;
+        next_id - 1
+    })).collect();
+    let new_id = |id: u32| new_ids.get(id as usize).copied().flatten();
+
+    let postscript = &tail.postscript;
+    let block_size = postscript.get_compressionBlockSize() as usize;
+    let compression: Compression = match postscript.get_compression() {
+        orc_proto::CompressionKind::ZSTD => ZstdCompression::new().with_block_size(block_size).build(),
+        orc_proto::CompressionKind::SNAPPY => SnappyCompression::new().with_block_size(block_size).build(),
+        _ => NoCompression::new().build(),
+    };
+    let config = Config::new()
+        .with_compression(compression)
+        .with_row_index_stride(tail.footer.get_rowIndexStride());
+    let mut writer = Writer::new(output, &Schema::Struct(kept_fields), config)?;
+    for item in tail.footer.get_metadata() {
+        writer.add_user_metadata(item.get_name(), item.get_value());
+    }
+
+    let metadata = tail.read_metadata(&mut input)?;
+    let stripes = tail.footer.get_stripes();
+    if metadata.get_stripeStats().len() != stripes.len() {
+        return Err(invalid_data("Missing stripe statistics"));
+    }
+    for (info, stripe_stats) in stripes.iter().zip(metadata.get_stripeStats()) {
+        let footer = read_stripe_footer(&mut input, tail.decompression(), info)?;
+        let mut new_footer = footer.clone();
+        let mut streams: Vec<orc_proto::Stream> = Vec::new();
+        let mut index_ranges = Vec::new();
+        let mut data_ranges = Vec::new();
+        let mut offset = info.get_offset();
+        for stream in footer.get_streams() {
+            let end = offset.checked_add(stream.get_length()).ok_or_else(|| invalid_data("Stream length overflows"))?;
+            let range = offset..end;
+            offset = range.end;
+            if let Some(id) = new_id(stream.get_column()) {
+                if range.start < info.get_offset() + info.get_indexLength() {
+                    index_ranges.push(range);
+                } else {
+                    data_ranges.push(range);
+                }
+                let mut stream = stream.clone();
+                stream.set_column(id);
+                streams.push(stream);
+            }
+        }
+        new_footer.set_streams(RepeatedField::from_vec(streams));
+        let encodings = footer.get_columns().iter().enumerate()
+            .filter(|(id, _)| new_id(*id as u32).is_some())
+            .map(|(_, encoding)| encoding.clone())
+            .collect();
+        new_footer.set_columns(RepeatedField::from_vec(encodings));
+        let statistics: Vec<_> = stripe_stats.get_colStats().iter().enumerate()
+            .filter(|(id, _)| new_id(*id as u32).is_some())
+            .map(|(_, stats)| stats.clone())
+            .collect();
+        writer.write_encoded_stripe(&mut input, &index_ranges, &data_ranges, &new_footer, 
+            info.get_numberOfRows(), &statistics)?;
+    }
+    writer.finish()
+}
+
+/// Prunes the fields of a struct column, given the paths of the selected or dropped fields relative 
+/// to it, and records for each column in pre-order whether it is kept.
+fn prune_fields(fields: &[Field], paths: &[&[&str]], drop: bool, kept: &mut Vec<bool>) -> Vec<Field> {
+    let mut kept_fields = Vec::new();
+    for Field(name, schema) in fields {
+        let paths: Vec<&[&str]> = paths.iter()
+            .filter(|path| path.first() == Some(&name.as_str()))
+            .map(|path| &path[1..])
+            .collect();
+        let whole = paths.iter().any(|path| path.is_empty());
+        match schema {
+            Schema::Struct(children) if !whole && !paths.is_empty() => {
+                kept.push(true);
+                let children = prune_fields(children, &paths, drop, kept);
+                kept_fields.push(Field(name.clone(), Schema::Struct(children)));
+            }
+            _ => {
+                let keep = whole != drop;
+                mark_columns(schema, keep, kept);
+                if keep {
+                    kept_fields.push(Field(name.clone(), schema.clone()));
+                }
+            }
+        }
+    }
+    kept_fields
+}
+
+fn mark_columns(schema: &Schema, keep: bool, kept: &mut Vec<bool>) {
+    kept.push(keep);
+    match schema {
+        Schema::Struct(fields) => fields.iter().for_each(|Field(_, schema)| mark_columns(schema, keep, kept)),
+        Schema::List(element) => mark_columns(element, keep, kept),
+        Schema::Map(key, value) => {
+            mark_columns(key, keep, kept);
+            mark_columns(value, keep, kept);
+        }
+        Schema::Union(variants) => variants.iter().for_each(|schema| mark_columns(schema, keep, kept)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::Reader;
+    use crate::value::Value;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_select_fields() {
+        let schema = Schema::Struct(vec![
+            Field("id".to_owned(), Schema::Long),
+            Field("email".to_owned(), Schema::String),
+            Field("tags".to_owned(), Schema::Map(Box::new(Schema::String), Box::new(Schema::Double))),
+            Field("ssn".to_owned(), Schema::List(Box::new(Schema::Int))),
+            Field("score".to_owned(), Schema::Double),
+        ]);
+        let row = |i: i64| Value::Struct(vec![
+            Value::Int(i),
+            if i % 5 == 0 { Value::Null } else { Value::String(format!("user{}@example.com", i)) },
+            Value::Map(vec![(Value::String(format!("t{}", i % 3)), Value::Double(i as f64))]),
+            Value::List(vec![Value::Int(i % 1000); (i % 4) as usize]),
+            Value::Double(i as f64 / 2.0),
+        ]);
+        let config = Config::new()
+            .with_compression(ZstdCompression::new().with_block_size(4096).build())
+            .with_stripe_row_limit(700)
+            .with_row_index_stride(100);
+        let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+        for i in 0..2000 {
+            writer.write_row(&row(i)).unwrap();
+        }
+        writer.add_user_metadata("owner", b"crm");
+        let input = writer.finish().unwrap();
+
+        let output = select_fields(Cursor::new(&input), Vec::new(), &["score", "id", "tags"]).unwrap();
+        let mut reader = Reader::new(Cursor::new(&output)).unwrap();
+        let Schema::Struct(fields) = &schema else { unreachable!() };
+        let kept = [0, 2, 4];
+        assert_eq!(reader.schema(), &Schema::Struct(kept.iter().map(|&i| fields[i].clone()).collect()));
+        assert_eq!(reader.num_stripes(), 3);
+        assert_eq!(reader.user_metadata(), vec![("owner", &b"crm"[..])]);
+        let expected: Vec<Value> = (0..2000).map(|i| match row(i) {
+            Value::Struct(values) => Value::Struct(kept.iter().map(|&i| values[i].clone()).collect()),
+            _ => unreachable!(),
+        }).collect();
+        assert_eq!(reader.rows().unwrap(), expected);
+
+        let tail = FileTail::read(&mut Cursor::new(&output), output.len() as u64).unwrap();
+        let stats = tail.footer.get_statistics();
+        assert_eq!(stats.len(), 6);
+        assert_eq!(stats[5].get_doubleStatistics().get_maximum(), 999.5);
+        assert!(select_fields(Cursor::new(&input), Vec::new(), &["name"]).is_err());
+    }
+
+    #[test]
+    fn test_nested_fields() {
+        let address = Schema::Struct(vec![
+            Field("city".to_owned(), Schema::String),
+            Field("zip".to_owned(), Schema::String),
+        ]);
+        let user = |address: Schema| Schema::Struct(vec![
+            Field("name".to_owned(), Schema::String),
+            Field("email".to_owned(), Schema::String),
+            Field("address".to_owned(), address),
+        ]);
+        let schema = Schema::Struct(vec![
+            Field("id".to_owned(), Schema::Long),
+            Field("user".to_owned(), user(address.clone())),
+            Field("tags".to_owned(), Schema::List(Box::new(Schema::String))),
+        ]);
+        let row = |i: i64| Value::Struct(vec![
+            Value::Int(i),
+            Value::Struct(vec![
+                Value::String(format!("user{}", i)),
+                Value::String(format!("user{}@example.com", i)),
+                if i % 3 == 0 { Value::Null } else {
+                    Value::Struct(vec![Value::String(format!("city{}", i % 7)), Value::String(format!("{:05}", i))])
+                },
+            ]),
+            Value::List(vec![Value::String(format!("t{}", i % 5)); (i % 3) as usize]),
+        ]);
+        let mut writer = Writer::new(Vec::new(), &schema, Config::new().with_stripe_row_limit(300)).unwrap();
+        for i in 0..1000 {
+            writer.write_row(&row(i)).unwrap();
+        }
+        let input = writer.finish().unwrap();
+
+        let output = drop_fields(Cursor::new(&input), Vec::new(), &["user.email", "user.address.zip"]).unwrap();
+        let mut reader = Reader::new(Cursor::new(&output)).unwrap();
+        assert_eq!(reader.schema(), &Schema::Struct(vec![
+            Field("id".to_owned(), Schema::Long),
+            Field("user".to_owned(), Schema::Struct(vec![
+                Field("name".to_owned(), Schema::String),
+                Field("address".to_owned(), Schema::Struct(vec![Field("city".to_owned(), Schema::String)])),
+            ])),
+            Field("tags".to_owned(), Schema::List(Box::new(Schema::String))),
+        ]));
+        let expected: Vec<Value> = (0..1000).map(|i| match row(i) {
+            Value::Struct(mut values) => {
+                if let Value::Struct(user) = &mut values[1] {
+                    user.remove(1);
+                    if let Value::Struct(address) = &mut user[1] {
+                        address.remove(1);
+                    }
+                }
+                Value::Struct(values)
+            }
+            _ => unreachable!(),
+        }).collect();
+        assert_eq!(reader.rows().unwrap(), expected);
+
+        let output = select_fields(Cursor::new(&input), Vec::new(), &["user.address", "tags", "user"]).unwrap();
+        let mut reader = Reader::new(Cursor::new(&output)).unwrap();
+        let Schema::Struct(fields) = &schema else { unreachable!() };
+        assert_eq!(reader.schema(), &Schema::Struct(fields[1..].to_vec()));
+        let output = select_fields(Cursor::new(&input), Vec::new(), &["user.address.zip", "id"]).unwrap();
+        let mut reader = Reader::new(Cursor::new(&output)).unwrap();
+        assert_eq!(reader.num_rows(), 1000);
+        let expected: Vec<Value> = (0..1000).map(|i| Value::Struct(vec![
+            Value::Int(i),
+            Value::Struct(vec![
+                if i % 3 == 0 { Value::Null } else { Value::Struct(vec![Value::String(format!("{:05}", i))]) },
+            ]),
+        ])).collect();
+        assert_eq!(reader.rows().unwrap(), expected);
+
+        for fields in [&["user.phone"][..], &["tags.name"], &["id.value"], &[""]] {
+            let err = drop_fields(Cursor::new(&input), Vec::new(), fields).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
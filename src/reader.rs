@@ -1,22 +1,219 @@
-// use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom, Result};
 
+use crate::protos::orc_proto;
+use crate::schema::Schema;
+use crate::value::Value;
+use column::{ColumnReader, StripeStreams};
+use compression::Decompression;
+use metadata::{FileTail, invalid_data, parse_message, read_range};
 
+pub(crate) mod compression;
+mod decoder;
+pub(crate) mod metadata;
+mod column;
 
+/// Reads ORC files produced by this crate's `Writer`, returning rows as dynamically-typed values.
+/// Only the encodings used by the writer (RLEv1 and DIRECT column encodings) and NONE, Snappy or 
+/// Zstd compression are supported; files using RLEv2 or dictionary encodings or ZLIB, LZO or LZ4 
+/// compression, as typically written by Hive and ORC Java, are rejected with an `InvalidData` error 
+/// naming the encoding, and for an encoding the column.
+pub struct Reader<R: Read + Seek> {
+    inner: R,
+    tail: FileTail,
+    schema: Schema,
+}
 
-// pub struct Reader<T: Read + Seek> {
-//     inner: T,
-// }
+impl<R: Read + Seek> Reader<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let file_len = inner.seek(SeekFrom::End(0))?;
+        let tail = FileTail::read(&mut inner, file_len)?;
+        let schema = tail.schema()?;
+        Ok(Reader { inner, tail, schema })
+    }
 
-// impl<T: Read + Seek> Reader<T> {
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
 
-// }
+    pub fn num_rows(&self) -> u64 {
+        self.tail.footer.get_numberOfRows()
+    }
 
-// pub struct LongReader {}
+    pub fn num_stripes(&self) -> usize {
+        self.tail.footer.get_stripes().len()
+    }
 
-// impl LongReader {
-//     pub fn skip(&mut self, values: u64) {}
+    /// The named values in the file's user metadata.
+    pub fn user_metadata(&self) -> Vec<(&str, &[u8])> {
+        self.tail.footer.get_metadata().iter().map(|item| (item.get_name(), item.get_value())).collect()
+    }
 
-//     pub fn read(&mut self) -> Option<i64> {
-//         unimplemented!()
-//     }
-// }
\ No newline at end of file
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads all rows of the stripe with the given index.
+    pub fn read_stripe(&mut self, index: usize) -> Result<Vec<Value>> {
+        let info = self.tail.footer.get_stripes().get(index)
+            .ok_or_else(|| invalid_data(format!("Invalid stripe index {}", index)))?
+            .clone();
+        read_stripe_rows(&mut self.inner, self.tail.decompression(), &self.schema, &info)
+    }
+
+    /// Reads all rows of the file.
+    pub fn rows(&mut self) -> Result<Vec<Value>> {
+        let mut rows: Vec<Value> = Vec::new();
+        for i in 0..self.num_stripes() {
+            rows.extend(self.read_stripe(i)?);
+        }
+        Ok(rows)
+    }
+}
+
+pub(crate) fn read_stripe_footer<R: Read + Seek>(
+    inner: &mut R, decompression: Decompression, info: &orc_proto::StripeInformation,
+) -> Result<orc_proto::StripeFooter> {
+    let footer_start = info.get_offset().checked_add(info.get_indexLength())
+        .and_then(|x| x.checked_add(info.get_dataLength()))
+        .ok_or_else(|| invalid_data("Invalid stripe lengths"))?;
+    let bytes = read_range(inner, footer_start, info.get_footerLength())?;
+    parse_message(&decompression.decompress(&bytes)?)
+}
+
+pub(crate) fn read_stripe_rows<R: Read + Seek>(
+    inner: &mut R, decompression: Decompression, schema: &Schema, info: &orc_proto::StripeInformation,
+) -> Result<Vec<Value>> {
+    let footer = read_stripe_footer(inner, decompression, info)?;
+    let len = info.get_indexLength().checked_add(info.get_dataLength())
+        .ok_or_else(|| invalid_data("Invalid stripe lengths"))?;
+    let bytes = read_range(inner, info.get_offset(), len)?;
+    decode_stripe_rows(&bytes, &footer, decompression, schema, info.get_numberOfRows())
+}
+
+/// Decodes the rows of a stripe, given its index and data streams (`bytes`) and its footer.
+pub(crate) fn decode_stripe_rows(
+    bytes: &[u8], footer: &orc_proto::StripeFooter, decompression: Decompression, schema: &Schema, num_rows: u64,
+) -> Result<Vec<Value>> {
+    let encodings = footer.get_columns().iter().map(|e| e.get_kind()).collect();
+    let mut streams = StripeStreams::new(encodings, schema);
+    let stripe_len = bytes.len() as u64;
+    let mut offset: u64 = 0;
+    for stream in footer.get_streams() {
+        let end = offset.checked_add(stream.get_length()).filter(|&end| end <= stripe_len)
+            .ok_or_else(|| invalid_data("Stream extends past the end of the stripe"))?;
+        if stream.get_kind() != orc_proto::Stream_Kind::ROW_INDEX {
+            let data = decompression.decompress(&bytes[offset as usize..end as usize])?;
+            streams.insert(stream.get_column(), stream.get_kind(), data);
+        }
+        offset = end;
+    }
+
+    let mut column_id = 0;
+    let mut reader = ColumnReader::new(schema, &mut streams, &mut column_id)?;
+    // The row count comes from the file, so no more is allocated up front than the stripe's size.
+    let mut rows: Vec<Value> = Vec::with_capacity(num_rows.min(stripe_len) as usize);
+    for _ in 0..num_rows {
+        rows.push(reader.next()?);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::schema::Field;
+    use crate::writer::{Config, Writer};
+    use crate::writer::compression::{NoCompression, SnappyCompression, ZstdCompression};
+
+    fn test_schema() -> Schema {
+        Schema::Struct(vec![
+            Field("b".to_owned(), Schema::Boolean),
+            Field("s".to_owned(), Schema::Short),
+            Field("l".to_owned(), Schema::Long),
+            Field("f".to_owned(), Schema::Float),
+            Field("d".to_owned(), Schema::Double),
+            Field("ts".to_owned(), Schema::Timestamp),
+            Field("dt".to_owned(), Schema::Date),
+            Field("dec".to_owned(), Schema::Decimal(10, 2)),
+            Field("str".to_owned(), Schema::String),
+            Field("bin".to_owned(), Schema::Binary),
+            Field("list".to_owned(), Schema::List(Box::new(Schema::Long))),
+            Field("map".to_owned(), Schema::Map(Box::new(Schema::String), Box::new(Schema::Double))),
+            Field("u".to_owned(), Schema::Union(vec![Schema::Long, Schema::String])),
+        ])
+    }
+
+    fn test_row(i: i64) -> Value {
+        if i % 10 == 3 {
+            let mut fields = vec![Value::Null; 13];
+            fields[0] = Value::Bool(true);
+            return Value::Struct(fields);
+        }
+        Value::Struct(vec![
+            Value::Bool(i % 3 == 0),
+            Value::Int(i % 1000),
+            Value::Int(i * 1000003),
+            Value::Double(i as f64 / 4.0),
+            Value::Double(i as f64 / 3.0),
+            Value::Timestamp(1600000000 + i, (i as u32 % 7) * 1000),
+            Value::Date(18000 + i as i32),
+            Value::Decimal(i as i128 * 101, 2),
+            Value::String(format!("row {}", i)),
+            Value::Binary(vec![i as u8; (i % 5) as usize]),
+            Value::List((0..(i % 4)).map(Value::Int).collect()),
+            Value::Map(vec![(Value::String("k".to_owned()), Value::Double(i as f64))]),
+            if i % 2 == 0 { Value::Union(0, Box::new(Value::Int(i))) }
+            else { Value::Union(1, Box::new(Value::String(i.to_string()))) },
+        ])
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let schema = test_schema();
+        let rows: Vec<Value> = (0..5000).map(test_row).collect();
+        for compression in vec![
+            NoCompression::new().build(),
+            SnappyCompression::new().build(),
+            ZstdCompression::new().build(),
+        ] {
+            let config = Config::new()
+                .with_compression(compression)
+                .with_row_index_stride(1000)
+                .with_stripe_size(20000);
+            let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+            for row in &rows {
+                writer.write_row(row).unwrap();
+            }
+            let out = writer.finish().unwrap();
+
+            let mut reader = Reader::new(Cursor::new(out)).unwrap();
+            assert_eq!(reader.schema(), &schema);
+            assert_eq!(reader.num_rows(), rows.len() as u64);
+            assert!(reader.num_stripes() > 1);
+            assert_eq!(reader.rows().unwrap(), rows);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_lengths() {
+        use orc_proto::ColumnEncoding_Kind::DIRECT;
+        use orc_proto::Stream_Kind::{DATA, LENGTH};
+
+        // A single literal length of u64::MAX, which the data streams cannot back.
+        let huge_length = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        for schema in [Schema::String, Schema::Binary, Schema::List(Box::new(Schema::Long))] {
+            let mut streams = StripeStreams::new(vec![DIRECT, DIRECT], &schema);
+            streams.insert(0, LENGTH, huge_length.clone());
+            streams.insert(0, DATA, b"abc".to_vec());
+            streams.insert(1, DATA, vec![0x00, 0x00, 0x02]);
+            let mut reader = ColumnReader::new(&schema, &mut streams, &mut 0).unwrap();
+            assert!(reader.next().is_err());
+        }
+
+        let bytes = Writer::new(Vec::new(), &test_schema(), Config::new()).unwrap().finish().unwrap();
+        let mut input = Cursor::new(&bytes);
+        assert_eq!(read_range(&mut input, 3, u64::MAX).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(read_range(&mut input, bytes.len() as u64, 1).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::io::Result;
+
+use crate::protos::orc_proto;
+use crate::schema::{Field, Schema};
+use crate::value::Value;
+use crate::writer::data::TimestampData;
+use super::decoder::{ByteReader, ByteRLEDecoder, BooleanRLEDecoder, IntRLEv1Decoder};
+use super::metadata::invalid_data;
+
+/// The decompressed streams of a stripe, keyed by column id and stream kind.
+pub(crate) struct StripeStreams<'a> {
+    streams: HashMap<(u32, orc_proto::Stream_Kind), Vec<u8>>,
+    encodings: Vec<orc_proto::ColumnEncoding_Kind>,
+    // For naming columns in errors
+    schema: &'a Schema,
+}
+
+impl<'a> StripeStreams<'a> {
+    pub fn new(encodings: Vec<orc_proto::ColumnEncoding_Kind>, schema: &'a Schema) -> Self {
+        StripeStreams {
+            streams: HashMap::new(),
+            encodings,
+            schema,
+        }
+    }
+
+    pub fn insert(&mut self, column_id: u32, kind: orc_proto::Stream_Kind, data: Vec<u8>) {
+        self.streams.insert((column_id, kind), data);
+    }
+
+    fn take(&mut self, column_id: u32, kind: orc_proto::Stream_Kind) -> Option<Vec<u8>> {
+        self.streams.remove(&(column_id, kind))
+    }
+
+    fn require(&mut self, column_id: u32, kind: orc_proto::Stream_Kind) -> Result<Vec<u8>> {
+        self.take(column_id, kind)
+            .ok_or_else(|| invalid_data(format!("Missing {:?} stream for column {}", kind, column_id)))
+    }
+
+    fn check_encoding(&self, column_id: u32) -> Result<()> {
+        match self.encodings.get(column_id as usize) {
+            Some(orc_proto::ColumnEncoding_Kind::DIRECT) => Ok(()),
+            Some(kind) => Err(invalid_data(format!("Unsupported encoding {:?} for column {}{}; only DIRECT can be read", 
+                kind, column_id, column_path(self.schema, column_id).map_or(String::new(), |p| format!(" ({})", p))))),
+            None => Err(invalid_data(format!("Missing encoding for column {}", column_id))),
+        }
+    }
+}
+
+/// The path of field names to a column, with `_elem`, `_key`, `_value` or the variant index for the 
+/// children of lists, maps and unions, or None for the root column or an id past the schema.
+fn column_path(schema: &Schema, column_id: u32) -> Option<String> {
+    fn find(schema: &Schema, target: u32, next_id: &mut u32, path: &mut Vec<String>) -> bool {
+        let id = *next_id;
+        *next_id += 1;
+        if id == target {
+            return true;
+        }
+        let children: Vec<(String, &Schema)> = match schema {
+            Schema::Struct(fields) => fields.iter().map(|Field(name, s)| (name.clone(), s)).collect(),
+            Schema::List(elem) => vec![("_elem".to_owned(), &**elem)],
+            Schema::Map(key, value) => vec![("_key".to_owned(), &**key), ("_value".to_owned(), &**value)],
+            Schema::Union(variants) => variants.iter().enumerate().map(|(i, s)| (i.to_string(), s)).collect(),
+            _ => vec![],
+        };
+        for (name, child) in children {
+            path.push(name);
+            if find(child, target, next_id, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+    let mut path: Vec<String> = Vec::new();
+    if column_id == 0 || !find(schema, column_id, &mut 0, &mut path) {
+        return None;
+    }
+    Some(path.join("."))
+}
+
+/// The most elements allocated up front for a list or map, whose length is read from the file and
+/// so cannot be trusted
+const MAX_PREALLOCATED: usize = 1024;
+
+/// Decodes the values of a column (and its descendants) from the streams of a stripe.
+pub(crate) struct ColumnReader {
+    present: Option<BooleanRLEDecoder>,
+    kind: ColumnKind,
+}
+
+enum ColumnKind {
+    Boolean(BooleanRLEDecoder),
+    Long(IntRLEv1Decoder),
+    Date(IntRLEv1Decoder),
+    Float(ByteReader),
+    Double(ByteReader),
+    Timestamp { seconds: IntRLEv1Decoder, nanos: IntRLEv1Decoder },
+    Decimal { data: ByteReader, scales: IntRLEv1Decoder },
+    String { data: ByteReader, lengths: IntRLEv1Decoder },
+    Binary { data: ByteReader, lengths: IntRLEv1Decoder },
+    Struct(Vec<ColumnReader>),
+    List { lengths: IntRLEv1Decoder, child: Box<ColumnReader> },
+    Map { lengths: IntRLEv1Decoder, keys: Box<ColumnReader>, values: Box<ColumnReader> },
+    Union { tags: ByteRLEDecoder, children: Vec<ColumnReader> },
+}
+
+impl ColumnReader {
+    pub fn new(schema: &Schema, streams: &mut StripeStreams, column_id: &mut u32) -> Result<Self> {
+        use orc_proto::Stream_Kind::{PRESENT, DATA, LENGTH, SECONDARY};
+
+        let cid = *column_id;
+        *column_id += 1;
+        streams.check_encoding(cid)?;
+        let present = streams.take(cid, PRESENT).map(BooleanRLEDecoder::new);
+        let signed = |streams: &mut StripeStreams, kind| -> Result<IntRLEv1Decoder> {
+            Ok(IntRLEv1Decoder::new(streams.require(cid, kind)?, true))
+        };
+        let unsigned = |streams: &mut StripeStreams, kind| -> Result<IntRLEv1Decoder> {
+            Ok(IntRLEv1Decoder::new(streams.require(cid, kind)?, false))
+        };
+        let bytes = |streams: &mut StripeStreams, kind| -> Result<ByteReader> {
+            Ok(ByteReader::new(streams.require(cid, kind)?))
+        };
+        let kind = match schema {
+            Schema::Boolean => ColumnKind::Boolean(BooleanRLEDecoder::new(streams.require(cid, DATA)?)),
+            Schema::Short | Schema::Int | Schema::Long => ColumnKind::Long(signed(streams, DATA)?),
+            Schema::Date => ColumnKind::Date(signed(streams, DATA)?),
+            Schema::Float => ColumnKind::Float(bytes(streams, DATA)?),
+            Schema::Double => ColumnKind::Double(bytes(streams, DATA)?),
+            Schema::Timestamp => ColumnKind::Timestamp {
+                seconds: signed(streams, DATA)?,
+                nanos: unsigned(streams, SECONDARY)?,
+            },
+            Schema::Decimal(_, _) => ColumnKind::Decimal {
+                data: bytes(streams, DATA)?,
+                scales: signed(streams, SECONDARY)?,
+            },
+            Schema::String | Schema::Char(_) | Schema::VarChar(_) => ColumnKind::String {
+                data: bytes(streams, DATA)?,
+                lengths: unsigned(streams, LENGTH)?,
+            },
+            Schema::Binary => ColumnKind::Binary {
+                data: bytes(streams, DATA)?,
+                lengths: unsigned(streams, LENGTH)?,
+            },
+            Schema::Struct(fields) => {
+                let mut children: Vec<ColumnReader> = Vec::new();
+                for field in fields {
+                    children.push(ColumnReader::new(&field.1, streams, column_id)?);
+                }
+                ColumnKind::Struct(children)
+            }
+            Schema::List(child) => ColumnKind::List {
+                lengths: unsigned(streams, LENGTH)?,
+                child: Box::new(ColumnReader::new(child, streams, column_id)?),
+            },
+            Schema::Map(keys, values) => ColumnKind::Map {
+                lengths: unsigned(streams, LENGTH)?,
+                keys: Box::new(ColumnReader::new(keys, streams, column_id)?),
+                values: Box::new(ColumnReader::new(values, streams, column_id)?),
+            },
+            Schema::Union(variants) => {
+                let tags = ByteRLEDecoder::new(streams.require(cid, DATA)?);
+                let mut children: Vec<ColumnReader> = Vec::new();
+                for variant in variants {
+                    children.push(ColumnReader::new(variant, streams, column_id)?);
+                }
+                ColumnKind::Union { tags, children }
+            }
+        };
+        Ok(ColumnReader { present, kind })
+    }
+
+    pub fn next(&mut self) -> Result<Value> {
+        if let Some(present) = &mut self.present {
+            if !present.read()? {
+                return Ok(Value::Null);
+            }
+        }
+        Ok(match &mut self.kind {
+            ColumnKind::Boolean(data) => Value::Bool(data.read()?),
+            ColumnKind::Long(data) => Value::Int(data.read_signed()?),
+            ColumnKind::Date(data) => Value::Date(data.read_signed()? as i32),
+            ColumnKind::Float(data) => {
+                let mut buf = [0; 4];
+                buf.copy_from_slice(data.read_bytes(4)?);
+                Value::Double(f32::from_le_bytes(buf) as f64)
+            }
+            ColumnKind::Double(data) => {
+                let mut buf = [0; 8];
+                buf.copy_from_slice(data.read_bytes(8)?);
+                Value::Double(f64::from_le_bytes(buf))
+            }
+            ColumnKind::Timestamp { seconds, nanos } => {
+                let sec = seconds.read_signed()? - TimestampData::EPOCH_SECONDS;
+                let encoded_nanos = nanos.read_unsigned()?;
+                let trailing_zeros = (encoded_nanos & 7) as u32;
+                let mut ns = (encoded_nanos >> 3) as u32;
+                if trailing_zeros > 0 {
+                    ns *= 10u32.pow(trailing_zeros + 1);
+                }
+                Value::Timestamp(sec, ns)
+            }
+            ColumnKind::Decimal { data, scales } => {
+                let x = data.read_varint_i128()?;
+                Value::Decimal(x, scales.read_signed()? as u32)
+            }
+            ColumnKind::String { data, lengths } => {
+                let len = data.check_length(lengths.read_unsigned()?)?;
+                let s = String::from_utf8(data.read_bytes(len)?.to_vec())
+                    .map_err(|_| invalid_data("Invalid UTF-8 in string column"))?;
+                Value::String(s)
+            }
+            ColumnKind::Binary { data, lengths } => {
+                let len = data.check_length(lengths.read_unsigned()?)?;
+                Value::Binary(data.read_bytes(len)?.to_vec())
+            }
+            ColumnKind::Struct(children) => {
+                let mut fields: Vec<Value> = Vec::with_capacity(children.len());
+                for child in children {
+                    fields.push(child.next()?);
+                }
+                Value::Struct(fields)
+            }
+            ColumnKind::List { lengths, child } => {
+                let len = lengths.read_unsigned()?;
+                let mut items: Vec<Value> = Vec::with_capacity((len as usize).min(MAX_PREALLOCATED));
+                for _ in 0..len {
+                    items.push(child.next()?);
+                }
+                Value::List(items)
+            }
+            ColumnKind::Map { lengths, keys, values } => {
+                let len = lengths.read_unsigned()?;
+                let mut entries: Vec<(Value, Value)> = Vec::with_capacity((len as usize).min(MAX_PREALLOCATED));
+                for _ in 0..len {
+                    entries.push((keys.next()?, values.next()?));
+                }
+                Value::Map(entries)
+            }
+            ColumnKind::Union { tags, children } => {
+                let tag = tags.read()?;
+                let child = children.get_mut(tag as usize)
+                    .ok_or_else(|| invalid_data(format!("Invalid union tag {}", tag)))?;
+                Value::Union(tag, Box::new(child.next()?))
+            }
+        })
+    }
+}
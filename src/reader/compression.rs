@@ -1,6 +1,73 @@
+use std::io::{Result, Error, ErrorKind};
 
-trait CompressionImpl {
-    fn decompressor(&self) -> Option<Box<dyn Decompressor>>;
+use crate::protos::orc_proto;
+
+/// Decompresses complete streams (or file tail sections), given the compression kind recorded
+/// in the PostScript.
+#[derive(Copy, Clone)]
+pub(crate) struct Decompression {
+    kind: orc_proto::CompressionKind,
 }
 
-pub struct Compression(Box<dyn CompressionImpl>);
+impl Decompression {
+    pub fn new(kind: orc_proto::CompressionKind) -> Result<Self> {
+        match kind {
+            orc_proto::CompressionKind::NONE |
+            orc_proto::CompressionKind::SNAPPY |
+            orc_proto::CompressionKind::ZSTD => Ok(Decompression { kind }),
+            _ => Err(Error::new(ErrorKind::InvalidData, format!(
+                "Unsupported compression kind {:?}; only NONE, SNAPPY and ZSTD can be read", kind))),
+        }
+    }
+
+    pub fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if self.kind == orc_proto::CompressionKind::NONE {
+            return Ok(input.to_vec());
+        }
+        let mut out: Vec<u8> = Vec::new();
+        let mut i = 0;
+        while i < input.len() {
+            if i + 3 > input.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "Truncated compression block header"));
+            }
+            let header = input[i] as usize | (input[i + 1] as usize) << 8 | (input[i + 2] as usize) << 16;
+            let is_original = header & 1 == 1;
+            let length = header >> 1;
+            i += 3;
+            if i + length > input.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "Truncated compression block"));
+            }
+            let block = &input[i..(i + length)];
+            if is_original {
+                out.extend_from_slice(block);
+            } else {
+                self.decompress_block(block, &mut out)?;
+            }
+            i += length;
+        }
+        Ok(out)
+    }
+
+    fn decompress_block(&self, block: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        match self.kind {
+            orc_proto::CompressionKind::SNAPPY => {
+                let invalid = |e| Error::new(ErrorKind::InvalidData, format!("Invalid Snappy block: {}", e));
+                let len = snap::decompress_len(block).map_err(invalid)?;
+                // Snappy expands each input byte to fewer than 32 output bytes, so a larger length 
+                // can only come from a corrupt header.
+                if len > block.len().saturating_mul(32) {
+                    return Err(Error::new(ErrorKind::InvalidData, "Invalid Snappy block length"));
+                }
+                let start = out.len();
+                out.resize(start + len, 0);
+                let actual_len = snap::Decoder::new().decompress(block, &mut out[start..]).map_err(invalid)?;
+                out.truncate(start + actual_len);
+            }
+            orc_proto::CompressionKind::ZSTD => {
+                out.extend(zstd::stream::decode_all(block)?);
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
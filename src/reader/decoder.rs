@@ -0,0 +1,233 @@
+use std::io::{Result, Error, ErrorKind};
+
+fn end_of_stream() -> Error {
+    Error::new(ErrorKind::UnexpectedEof, "Unexpected end of stream")
+}
+
+/// Cursor over the bytes of a fully decompressed stream.
+pub(crate) struct ByteReader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl ByteReader {
+    pub fn new(data: Vec<u8>) -> Self {
+        ByteReader { data, pos: 0 }
+    }
+
+    /// Checks a length read from the file against the bytes remaining in the stream.
+    pub fn check_length(&self, len: u64) -> Result<usize> {
+        if len > (self.data.len() - self.pos) as u64 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Length {} exceeds the remaining stream", len)));
+        }
+        Ok(len as usize)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        let b = *self.data.get(self.pos).ok_or_else(end_of_stream)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&[u8]> {
+        // `len` may come from a corrupt file, so it is compared without adding to it.
+        if len > self.data.len() - self.pos {
+            return Err(end_of_stream());
+        }
+        let bytes = &self.data[self.pos..(self.pos + len)];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn read_varint_u64(&mut self) -> Result<u64> {
+        let mut x: u64 = 0;
+        for i in 0..10 {
+            let b = self.read_u8()?;
+            x |= ((b & 0x7f) as u64) << (7 * i);
+            if b < 0x80 {
+                return Ok(x);
+            }
+        }
+        Err(Error::new(ErrorKind::InvalidData, "Varint too long"))
+    }
+
+    pub fn read_varint_i64(&mut self) -> Result<i64> {
+        let x = self.read_varint_u64()?;
+        Ok((x >> 1) as i64 ^ -((x & 1) as i64))
+    }
+
+    pub fn read_varint_i128(&mut self) -> Result<i128> {
+        let mut x: u128 = 0;
+        for i in 0..19 {
+            let b = self.read_u8()?;
+            x |= ((b & 0x7f) as u128) << (7 * i);
+            if b < 0x80 {
+                return Ok((x >> 1) as i128 ^ -((x & 1) as i128));
+            }
+        }
+        Err(Error::new(ErrorKind::InvalidData, "Varint too long"))
+    }
+}
+
+pub(crate) struct ByteRLEDecoder {
+    inner: ByteReader,
+    // Number of values left in the current run or literal group
+    remaining: usize,
+    is_run: bool,
+    run_value: u8,
+}
+
+impl ByteRLEDecoder {
+    pub fn new(data: Vec<u8>) -> Self {
+        ByteRLEDecoder {
+            inner: ByteReader::new(data),
+            remaining: 0,
+            is_run: false,
+            run_value: 0,
+        }
+    }
+
+    pub fn read(&mut self) -> Result<u8> {
+        if self.remaining == 0 {
+            let header = self.inner.read_u8()? as i8;
+            if header >= 0 {
+                self.is_run = true;
+                self.remaining = header as usize + 3;
+                self.run_value = self.inner.read_u8()?;
+            } else {
+                self.is_run = false;
+                self.remaining = -(header as isize) as usize;
+            }
+        }
+        self.remaining -= 1;
+        if self.is_run {
+            Ok(self.run_value)
+        } else {
+            self.inner.read_u8()
+        }
+    }
+}
+
+pub(crate) struct BooleanRLEDecoder {
+    byte_rle: ByteRLEDecoder,
+    buf: u8,
+    cnt: u8,
+}
+
+impl BooleanRLEDecoder {
+    pub fn new(data: Vec<u8>) -> Self {
+        BooleanRLEDecoder {
+            byte_rle: ByteRLEDecoder::new(data),
+            buf: 0,
+            cnt: 0,
+        }
+    }
+
+    pub fn read(&mut self) -> Result<bool> {
+        if self.cnt == 0 {
+            self.buf = self.byte_rle.read()?;
+            self.cnt = 8;
+        }
+        self.cnt -= 1;
+        Ok(self.buf >> self.cnt & 1 == 1)
+    }
+}
+
+pub(crate) struct IntRLEv1Decoder {
+    inner: ByteReader,
+    signed: bool,
+    remaining: usize,
+    is_run: bool,
+    delta: i64,
+    next_value: i64,
+}
+
+impl IntRLEv1Decoder {
+    pub fn new(data: Vec<u8>, signed: bool) -> Self {
+        IntRLEv1Decoder {
+            inner: ByteReader::new(data),
+            signed,
+            remaining: 0,
+            is_run: false,
+            delta: 0,
+            next_value: 0,
+        }
+    }
+
+    fn read_varint(&mut self) -> Result<i64> {
+        if self.signed {
+            self.inner.read_varint_i64()
+        } else {
+            Ok(self.inner.read_varint_u64()? as i64)
+        }
+    }
+
+    fn read_i64(&mut self) -> Result<i64> {
+        if self.remaining == 0 {
+            let header = self.inner.read_u8()? as i8;
+            if header >= 0 {
+                self.is_run = true;
+                self.remaining = header as usize + 3;
+                self.delta = self.inner.read_u8()? as i8 as i64;
+                self.next_value = self.read_varint()?;
+            } else {
+                self.is_run = false;
+                self.remaining = -(header as isize) as usize;
+            }
+        }
+        self.remaining -= 1;
+        if self.is_run {
+            let x = self.next_value;
+            self.next_value = x.wrapping_add(self.delta);
+            Ok(x)
+        } else {
+            self.read_varint()
+        }
+    }
+
+    pub fn read_signed(&mut self) -> Result<i64> {
+        self.read_i64()
+    }
+
+    pub fn read_unsigned(&mut self) -> Result<u64> {
+        Ok(self.read_i64()? as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::compression::NoCompression;
+    use crate::writer::encoder::{SignedIntRLEv1, BooleanRLE};
+
+    #[test]
+    fn test_int_rle_v1_roundtrip() {
+        let input: Vec<i64> = vec![5, 5, 5, 5, 1, -7, 100000, 3, 6, 9, 12, -1, -1, i64::MIN, i64::MAX];
+        let mut rle = SignedIntRLEv1::new(&NoCompression::new().build());
+        for &x in &input {
+            rle.write(x);
+        }
+        let mut out: Vec<u8> = Vec::new();
+        rle.finish(&mut out).unwrap();
+        let mut decoder = IntRLEv1Decoder::new(out, true);
+        for &x in &input {
+            assert_eq!(decoder.read_signed().unwrap(), x);
+        }
+        assert!(decoder.read_signed().is_err());
+    }
+
+    #[test]
+    fn test_boolean_rle_roundtrip() {
+        let input: Vec<bool> = (0..1000).map(|i| i % 7 == 0 || i > 500).collect();
+        let mut rle = BooleanRLE::new(&NoCompression::new().build());
+        for &x in &input {
+            rle.write(x);
+        }
+        let mut out: Vec<u8> = Vec::new();
+        rle.finish(&mut out).unwrap();
+        let mut decoder = BooleanRLEDecoder::new(out);
+        for &x in &input {
+            assert_eq!(decoder.read().unwrap(), x);
+        }
+    }
+}
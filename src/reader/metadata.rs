@@ -1,16 +1,191 @@
-use crate::schema::Schema;
-use crate::reader::compression::Compression;
+use std::io::{Read, Seek, SeekFrom, Result, Error, ErrorKind};
+use protobuf::Message;
 
-pub struct Compression {
+use crate::protos::orc_proto;
+use crate::schema::{Schema, Field};
+use super::compression::Decompression;
 
+/// The parsed tail of an ORC file: the PostScript and Footer sections.
+pub(crate) struct FileTail {
+    pub postscript: orc_proto::PostScript,
+    pub footer: orc_proto::Footer,
+    /// The offset of the Metadata section, where the tail starts
+    pub metadata_start: u64,
 }
 
-pub struct Splice {
+pub(crate) fn invalid_data<T: Into<String>>(msg: T) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+pub(crate) fn parse_message<M: Message>(bytes: &[u8]) -> Result<M> {
+    M::parse_from_bytes(bytes).map_err(|e| invalid_data(format!("Invalid protobuf message: {}", e)))
+}
 
+/// Reads `len` bytes at `offset`, which must lie within the input: lengths read from a file are
+/// checked before anything is allocated for them.
+pub(crate) fn read_range<R: Read + Seek>(inner: &mut R, offset: u64, len: u64) -> Result<Vec<u8>> {
+    let input_len = inner.seek(SeekFrom::End(0))?;
+    if offset.checked_add(len).is_none_or(|end| end > input_len) {
+        return Err(invalid_data(format!("Range of {} bytes at offset {} extends past the end of the file", len, offset)));
+    }
+    inner.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0; len as usize];
+    inner.read_exact(&mut buf)?;
+    Ok(buf)
 }
 
-pub struct Metadata {
-    schema: Schema;
-    compression: Compression;
-    splices: Vec<Splice>;
-}
\ No newline at end of file
+impl FileTail {
+    /// Parses the tail of a file whose last byte is at `file_len - 1`.
+    pub fn read<R: Read + Seek>(inner: &mut R, file_len: u64) -> Result<Self> {
+        if file_len < 4 {
+            return Err(invalid_data("File is too short to be an ORC file"));
+        }
+        let header = read_range(inner, 0, 3)?;
+        if header != b"ORC" {
+            return Err(invalid_data("File does not start with the ORC header"));
+        }
+        let postscript_len = read_range(inner, file_len - 1, 1)?[0] as u64;
+        if postscript_len + 1 > file_len {
+            return Err(invalid_data("Invalid PostScript length"));
+        }
+        let postscript_start = file_len - 1 - postscript_len;
+        let postscript: orc_proto::PostScript = parse_message(&read_range(inner, postscript_start, postscript_len)?)?;
+        if postscript.get_magic() != "ORC" {
+            return Err(invalid_data("Invalid PostScript magic"));
+        }
+        let decompression = Decompression::new(postscript.get_compression())?;
+
+        let footer_len = postscript.get_footerLength();
+        let metadata_len = postscript.get_metadataLength();
+        if footer_len + metadata_len > postscript_start {
+            return Err(invalid_data("Invalid Footer or Metadata length"));
+        }
+        let footer_start = postscript_start - footer_len;
+        let footer: orc_proto::Footer = parse_message(
+            &decompression.decompress(&read_range(inner, footer_start, footer_len)?)?)?;
+
+        let metadata_start = footer_start - metadata_len;
+        Ok(FileTail { postscript, footer, metadata_start })
+    }
+
+    /// Reads the Metadata section, which holds the statistics of each stripe.
+    pub fn read_metadata<R: Read + Seek>(&self, inner: &mut R) -> Result<orc_proto::Metadata> {
+        let bytes = read_range(inner, self.metadata_start, self.postscript.get_metadataLength())?;
+        parse_message(&self.decompression().decompress(&bytes)?)
+    }
+
+    pub fn decompression(&self) -> Decompression {
+        // The compression kind was already validated when reading the tail.
+        Decompression::new(self.postscript.get_compression()).unwrap()
+    }
+
+    pub fn schema(&self) -> Result<Schema> {
+        let schema = schema_from_types(self.footer.get_types())?;
+        schema.check().map_err(|e| invalid_data(e.to_string()))?;
+        Ok(schema)
+    }
+}
+
+/// Reads the schema from the types of a footer, which must be numbered in pre-order: each type's 
+/// subtypes are the next unused ids, so that every column has a single place in the tree.
+pub(crate) fn schema_from_types(types: &[orc_proto::Type]) -> Result<Schema> {
+    let mut next_id = 0;
+    let schema = read_type(types, &mut next_id)?;
+    if next_id as usize != types.len() {
+        return Err(invalid_data("Unused types in type tree"));
+    }
+    Ok(schema)
+}
+
+fn read_type(types: &[orc_proto::Type], next_id: &mut u32) -> Result<Schema> {
+    let t = types.get(*next_id as usize).ok_or_else(|| invalid_data("Invalid column id in type tree"))?;
+    *next_id += 1;
+    let subtypes = t.get_subtypes();
+    let subtype = |i: usize, next_id: &mut u32| -> Result<Schema> {
+        let id = *subtypes.get(i).ok_or_else(|| invalid_data("Missing subtype in type tree"))?;
+        if id != *next_id {
+            return Err(invalid_data("Subtypes in type tree are not numbered in pre-order"));
+        }
+        read_type(types, next_id)
+    };
+    let schema = match t.get_kind() {
+        orc_proto::Type_Kind::BOOLEAN => Schema::Boolean,
+        orc_proto::Type_Kind::SHORT => Schema::Short,
+        orc_proto::Type_Kind::INT => Schema::Int,
+        orc_proto::Type_Kind::LONG => Schema::Long,
+        orc_proto::Type_Kind::DATE => Schema::Date,
+        orc_proto::Type_Kind::FLOAT => Schema::Float,
+        orc_proto::Type_Kind::DOUBLE => Schema::Double,
+        orc_proto::Type_Kind::TIMESTAMP => Schema::Timestamp,
+        orc_proto::Type_Kind::DECIMAL => Schema::Decimal(t.get_precision(), t.get_scale()),
+        orc_proto::Type_Kind::STRING => Schema::String,
+        orc_proto::Type_Kind::BINARY => Schema::Binary,
+        orc_proto::Type_Kind::CHAR => Schema::Char(t.get_maximumLength()),
+        orc_proto::Type_Kind::VARCHAR => Schema::VarChar(t.get_maximumLength()),
+        orc_proto::Type_Kind::STRUCT => {
+            let names = t.get_fieldNames();
+            if names.len() != subtypes.len() {
+                return Err(invalid_data("Mismatched field names in type tree"));
+            }
+            let mut fields: Vec<Field> = Vec::new();
+            for (i, name) in names.iter().enumerate() {
+                fields.push(Field(name.clone(), subtype(i, next_id)?));
+            }
+            Schema::Struct(fields)
+        }
+        orc_proto::Type_Kind::LIST => Schema::List(Box::new(subtype(0, next_id)?)),
+        orc_proto::Type_Kind::MAP => Schema::Map(Box::new(subtype(0, next_id)?), Box::new(subtype(1, next_id)?)),
+        orc_proto::Type_Kind::UNION => {
+            let mut variants: Vec<Schema> = Vec::new();
+            for i in 0..subtypes.len() {
+                variants.push(subtype(i, next_id)?);
+            }
+            Schema::Union(variants)
+        }
+        kind => return Err(invalid_data(format!("Unsupported type {:?}", kind))),
+    };
+    let num_subtypes = match &schema {
+        Schema::Struct(fields) => fields.len(),
+        Schema::List(_) => 1,
+        Schema::Map(_, _) => 2,
+        Schema::Union(variants) => variants.len(),
+        _ => 0,
+    };
+    if subtypes.len() != num_subtypes {
+        return Err(invalid_data("Unexpected subtypes in type tree"));
+    }
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::RepeatedField;
+
+    fn make_type(kind: orc_proto::Type_Kind, subtypes: &[u32]) -> orc_proto::Type {
+        let mut t = orc_proto::Type::new();
+        t.set_kind(kind);
+        t.set_subtypes(subtypes.to_vec());
+        if kind == orc_proto::Type_Kind::STRUCT {
+            t.set_fieldNames(RepeatedField::from_vec((0..subtypes.len()).map(|i| format!("f{}", i)).collect()));
+        }
+        t
+    }
+
+    #[test]
+    fn test_schema_from_types() {
+        use orc_proto::Type_Kind::{INT, LIST, STRUCT};
+        let types = vec![make_type(STRUCT, &[1, 2]), make_type(INT, &[]), make_type(LIST, &[3]), make_type(INT, &[])];
+        assert_eq!(schema_from_types(&types).unwrap(), Schema::Struct(vec![
+            Field("f0".to_owned(), Schema::Int),
+            Field("f1".to_owned(), Schema::List(Box::new(Schema::Int))),
+        ]));
+        // Shared, out of order, missing and unused subtypes
+        for subtypes in [&[1, 1][..], &[2, 1], &[1, 3]] {
+            let types = vec![make_type(STRUCT, subtypes), make_type(INT, &[]), make_type(INT, &[]), make_type(INT, &[])];
+            assert_eq!(schema_from_types(&types).err().unwrap().kind(), ErrorKind::InvalidData);
+        }
+        let types = vec![make_type(LIST, &[1]), make_type(INT, &[]), make_type(INT, &[])];
+        assert_eq!(schema_from_types(&types).err().unwrap().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,127 @@
+//! Statically-typed writing of Rust structs. With the `derive` feature, `#[derive(OrcRecord)]`
+//! generates the schema of a struct together with code that writes its fields directly to the
+//! corresponding columns:
+//!
+//! ```ignore
+//! #[derive(OrcRecord)]
+//! struct User {
+//!     id: i64,
+//!     #[orc(rename = "user_name")]
+//!     name: String,
+//!     email: Option<String>,
+//!     tags: Vec<String>,
+//!     address: Address,  // another struct deriving `OrcRecord`
+//! }
+//! ```
+
+use crate::schema::Schema;
+use crate::writer::data::{Data, GenericData, StructData};
+
+#[cfg(feature = "derive")]
+pub use orc_format_derive::OrcRecord;
+
+/// A Rust type which can be written as a value of an ORC column.
+pub trait OrcField {
+    fn schema() -> Schema;
+
+    /// Writes `self` as the next value of the column. Panics if the column does not have the
+    /// type given by `Self::schema()`.
+    fn write_field(&self, data: &mut Data);
+}
+
+/// A Rust struct which maps onto an ORC struct column.
+pub trait OrcRecord {
+    fn schema() -> Schema;
+
+    /// Writes `self` as the next (non-null) value of the struct column.
+    fn write_to(&self, data: &mut StructData);
+}
+
+impl OrcField for bool {
+    fn schema() -> Schema { Schema::Boolean }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_boolean().write(*self);
+    }
+}
+
+impl OrcField for i16 {
+    fn schema() -> Schema { Schema::Short }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_long().write_i16(*self);
+    }
+}
+
+impl OrcField for i32 {
+    fn schema() -> Schema { Schema::Int }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_long().write_i32(*self);
+    }
+}
+
+impl OrcField for i64 {
+    fn schema() -> Schema { Schema::Long }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_long().write(*self);
+    }
+}
+
+impl OrcField for f32 {
+    fn schema() -> Schema { Schema::Float }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_float().write(*self);
+    }
+}
+
+impl OrcField for f64 {
+    fn schema() -> Schema { Schema::Double }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_double().write(*self);
+    }
+}
+
+impl OrcField for String {
+    fn schema() -> Schema { Schema::String }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_string().write(self);
+    }
+}
+
+/// Byte vectors are written as `Binary` values rather than as lists.
+impl OrcField for Vec<u8> {
+    fn schema() -> Schema { Schema::Binary }
+
+    fn write_field(&self, data: &mut Data) {
+        data.unwrap_binary().write(self);
+    }
+}
+
+impl<T: OrcField> OrcField for Option<T> {
+    fn schema() -> Schema { T::schema() }
+
+    fn write_field(&self, data: &mut Data) {
+        match self {
+            Some(x) => x.write_field(data),
+            None => data.write_null(),
+        }
+    }
+}
+
+impl<T: OrcField> OrcField for Vec<T> {
+    fn schema() -> Schema { Schema::List(Box::new(T::schema())) }
+
+    fn write_field(&self, data: &mut Data) {
+        let list = data.unwrap_list();
+        list.write(self.len() as u64);
+        let child = list.child();
+        for x in self {
+            x.write_field(child);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Recovery of files left incomplete by a writer which did not get to finish, and salvage of the 
+//! intact stripes of damaged files.
+
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use byteorder::{BigEndian, ByteOrder};
+use protobuf::CodedInputStream;
+use protobuf::wire_format::WireType;
+
+use crate::protos::orc_proto;
+use crate::reader::decode_stripe_rows;
+use crate::reader::compression::Decompression;
+use crate::reader::metadata::{FileTail, invalid_data, parse_message, read_range};
+use crate::schema::Schema;
+use crate::writer::Writer;
+use crate::writer::compression::{Compression, NoCompression, SnappyCompression, ZstdCompression};
+
+/// The path of the side file of flush lengths for the file at `path`, as used by Hive:
+/// `<path>_flush_length`.
+pub fn flush_length_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push("_flush_length");
+    PathBuf::from(name)
+}
+
+/// Restores the file at `path`, written with flush lengths recorded in `flush_length_path(path)`
+/// (see `Writer::with_flush_lengths`), to the last recorded length. Since each recorded length
+/// ends with a complete file tail, the truncated file is a valid ORC file containing every stripe
+/// written up to that point. The tail is checked before truncating, so that a stale or foreign 
+/// side file leaves the file as it is, and the side file is removed afterwards. Returns the 
+/// recovered length.
+pub fn recover(path: &Path) -> Result<u64> {
+    let side_path = flush_length_path(path);
+    let lengths = fs::read(&side_path)?;
+    // A partially written last entry is ignored.
+    let len = match lengths.chunks_exact(8).last() {
+        Some(entry) => BigEndian::read_u64(entry),
+        None => return Err(Error::new(ErrorKind::InvalidData, "No length has been recorded in the flush length file")),
+    };
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    if file.metadata()?.len() < len {
+        return Err(Error::new(ErrorKind::InvalidData, format!(
+            "File is shorter than its recorded length {}", len)));
+    }
+    FileTail::read(&mut file, len)?;
+    file.set_len(len)?;
+    file.sync_all()?;
+    fs::remove_file(&side_path)?;
+    Ok(len)
+}
+
+/// The stretch of the file in which a stripe footer must fit to be found by searching.
+const FOOTER_LOOKAHEAD: u64 = 1 << 20;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The intact stripes of a damaged file (e.g. one truncated by a failed upload), found by 
+/// `Salvage::scan`.
+pub struct Salvage<R> {
+    input: R,
+    file_len: u64,
+    schema: Schema,
+    num_columns: usize,
+    kind: orc_proto::CompressionKind,
+    stripes: Vec<SalvagedStripe>,
+}
+
+struct SalvagedStripe {
+    offset: u64,
+    // Length of the index and data streams
+    length: u64,
+    footer: orc_proto::StripeFooter,
+    footer_length: u64,
+    num_rows: u64,
+}
+
+impl<R: Read + Seek> Salvage<R> {
+    /// Finds the intact stripes of a damaged file, whose tail may be missing or corrupt. Since the 
+    /// file's own schema is in its tail, the schema must be supplied. The compression kind is 
+    /// detected from the first stripe, which starts right after the `ORC` header. Stripes are then 
+    /// read one at a time, by following the compression chunks of their streams up to a chunk 
+    /// holding a stripe footer whose streams account for exactly the bytes before it. Without 
+    /// compression, or where the chunks stop making sense, the file is searched for the next stripe 
+    /// footer instead, and the stripe found from the lengths of its streams, which skips over 
+    /// damaged parts of the file. A stripe is kept if its rows can be decoded.
+    pub fn scan(mut input: R, schema: &Schema) -> Result<Salvage<R>> {
+        let file_len = input.seek(SeekFrom::End(0))?;
+        if file_len < 3 || read_range(&mut input, 0, 3)? != b"ORC" {
+            return Err(invalid_data("File does not start with the ORC header"));
+        }
+        let mut num_columns = 0;
+        count_columns(schema, &mut num_columns);
+        let mut salvage = Salvage {
+            input,
+            file_len,
+            schema: schema.clone(),
+            num_columns,
+            kind: orc_proto::CompressionKind::NONE,
+            stripes: Vec::new(),
+        };
+        salvage.kind = salvage.detect_compression()?;
+        let mut offset = 3;
+        while let Some(stripe) = salvage.find_stripe(offset)? {
+            offset = stripe.offset + stripe.length + stripe.footer_length;
+            salvage.stripes.push(stripe);
+        }
+        Ok(salvage)
+    }
+
+    pub fn num_stripes(&self) -> usize {
+        self.stripes.len()
+    }
+
+    pub fn num_rows(&self) -> u64 {
+        self.stripes.iter().map(|s| s.num_rows).sum()
+    }
+
+    /// Offset of the end of the last salvaged stripe.
+    pub fn salvaged_length(&self) -> u64 {
+        match self.stripes.last() {
+            Some(s) => s.offset + s.length + s.footer_length,
+            None => 3,
+        }
+    }
+
+    /// The compression of the damaged file (with the default block size), for writing the 
+    /// salvaged rows in the same way.
+    pub fn compression(&self) -> Compression {
+        match self.kind {
+            orc_proto::CompressionKind::ZSTD => ZstdCompression::new().build(),
+            orc_proto::CompressionKind::SNAPPY => SnappyCompression::new().build(),
+            _ => NoCompression::new().build(),
+        }
+    }
+
+    /// Writes the rows of the salvaged stripes to `writer`, whose schema must be the one given to 
+    /// `scan`. Finishing the writer then gives a valid file, with statistics computed afresh.
+    pub fn write_to<W: Write>(&mut self, writer: &mut Writer<W>) -> Result<()> {
+        for stripe in &self.stripes {
+            let bytes = read_range(&mut self.input, stripe.offset, stripe.length)?;
+            let decompression = Decompression::new(self.kind)?;
+            for row in decode_stripe_rows(&bytes, &stripe.footer, decompression, &self.schema, stripe.num_rows)? {
+                writer.write_row(&row)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decompression(&self) -> Decompression {
+        Decompression::new(self.kind).unwrap()
+    }
+
+    /// Works out the compression kind from the start of the first stripe, the row index of the root 
+    /// column, which is either a bare protobuf message or a compression chunk. Chunks stored 
+    /// uncompressed are skipped until one that is compressed tells zstd and Snappy apart; if there 
+    /// is none, zstd is assumed.
+    fn detect_compression(&mut self) -> Result<orc_proto::CompressionKind> {
+        let mut offset = 3;
+        while let Some((length, is_original)) = self.chunk_header(offset)? {
+            let chunk = read_range(&mut self.input, offset, 3 + length)?;
+            if is_original {
+                if offset == 3 && parse_message::<orc_proto::RowIndex>(&chunk[3..]).is_err() {
+                    break;
+                }
+            } else {
+                let kind = if chunk[3..].starts_with(&ZSTD_MAGIC) {
+                    orc_proto::CompressionKind::ZSTD
+                } else {
+                    orc_proto::CompressionKind::SNAPPY
+                };
+                match Decompression::new(kind)?.decompress(&chunk) {
+                    Ok(bytes) if offset > 3 || parse_message::<orc_proto::RowIndex>(&bytes).is_ok() => return Ok(kind),
+                    _ => break,
+                }
+            }
+            offset += 3 + length;
+        }
+        Ok(if offset > 3 { orc_proto::CompressionKind::ZSTD } else { orc_proto::CompressionKind::NONE })
+    }
+
+    /// Reads the header of the compression chunk at `offset`, returning its length and whether it 
+    /// is stored uncompressed, or `None` if the chunk would extend past the end of the file.
+    fn chunk_header(&mut self, offset: u64) -> Result<Option<(u64, bool)>> {
+        if offset + 3 > self.file_len {
+            return Ok(None);
+        }
+        let header = read_range(&mut self.input, offset, 3)?;
+        let (length, is_original) = parse_chunk_header(&header);
+        Ok(if offset + 3 + length <= self.file_len { Some((length, is_original)) } else { None })
+    }
+
+    /// Finds the first intact stripe which starts at or after `offset`.
+    fn find_stripe(&mut self, offset: u64) -> Result<Option<SalvagedStripe>> {
+        let mut start = offset;
+        if self.kind != orc_proto::CompressionKind::NONE {
+            // A chunk is tried as the footer once the streams before it add up. A damaged stripe 
+            // whose footer is intact is skipped.
+            let mut chunk_start = start;
+            while let Some((length, _)) = self.chunk_header(chunk_start)? {
+                let chunk = read_range(&mut self.input, chunk_start, 3 + length)?;
+                let bytes = match self.decompression().decompress(&chunk) {
+                    Ok(bytes) => bytes,
+                    Err(_) => break,
+                };
+                let footer_start = chunk_start;
+                chunk_start += 3 + length;
+                let footer = match parse_message::<orc_proto::StripeFooter>(&bytes) {
+                    Ok(footer) => footer,
+                    Err(_) => continue,
+                };
+                if self.streams_length(&footer) == Some(footer_start - start) {
+                    if let Some(stripe) = self.check_stripe(start, footer_start, footer, 3 + length)? {
+                        return Ok(Some(stripe));
+                    }
+                    start = chunk_start;
+                }
+            }
+        }
+        self.search_stripe(start)
+    }
+
+    /// Searches the file from `offset` for a stripe footer, taking the stripe to be the bytes before 
+    /// it which its streams account for.
+    fn search_stripe(&mut self, offset: u64) -> Result<Option<SalvagedStripe>> {
+        let mut window = Window { start: 0, bytes: Vec::new() };
+        for footer_start in offset..self.file_len {
+            let bytes = window.at(&mut self.input, footer_start, self.file_len)?;
+            let (footer, footer_length) = match self.parse_footer(bytes) {
+                Some(x) => x,
+                None => continue,
+            };
+            let streams_length = match self.streams_length(&footer) {
+                Some(len) if len <= footer_start - offset => len,
+                _ => continue,
+            };
+            if let Some(stripe) = self.check_stripe(footer_start - streams_length, footer_start, footer, footer_length)? {
+                return Ok(Some(stripe));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parses a stripe footer at the start of `bytes`, returning it with its length.
+    fn parse_footer(&self, bytes: &[u8]) -> Option<(orc_proto::StripeFooter, u64)> {
+        if self.kind != orc_proto::CompressionKind::NONE {
+            // The footer is a single chunk, which gives its length. The start of the chunk rules 
+            // out most offsets before anything is decompressed.
+            let (length, is_original) = parse_chunk_header(bytes.get(..3)?);
+            let chunk = bytes.get(..3 + length as usize)?;
+            let plausible = if is_original {
+                chunk.get(3) == Some(&0x0a)
+            } else if self.kind == orc_proto::CompressionKind::ZSTD {
+                chunk[3..].starts_with(&ZSTD_MAGIC)
+            } else {
+                snap::decompress_len(&chunk[3..]).is_ok_and(|len| len as u64 <= FOOTER_LOOKAHEAD)
+            };
+            if !plausible {
+                return None;
+            }
+            let footer = parse_message(&self.decompression().decompress(chunk).ok()?).ok()?;
+            return Some((footer, 3 + length));
+        }
+
+        // Without compression, the footer's length is not known, so it is parsed field by field 
+        // until it has an encoding for every column. Its streams come first.
+        if bytes.first() != Some(&0x0a) {
+            return None;
+        }
+        let mut input = CodedInputStream::from_bytes(bytes);
+        let mut footer = orc_proto::StripeFooter::new();
+        while !input.eof().ok()? && footer.get_columns().len() < self.num_columns {
+            match input.read_tag_unpack().ok()? {
+                (1, WireType::WireTypeLengthDelimited) => footer.mut_streams().push(input.read_message().ok()?),
+                (2, WireType::WireTypeLengthDelimited) => footer.mut_columns().push(input.read_message().ok()?),
+                _ => return None,
+            }
+        }
+        // The writer's time zone may follow.
+        let mut end = input.pos();
+        if let Ok((3, WireType::WireTypeLengthDelimited)) = input.read_tag_unpack() {
+            if let Ok(time_zone) = input.read_string() {
+                footer.set_writerTimezone(time_zone);
+                end = input.pos();
+            }
+        }
+        Some((footer, end))
+    }
+
+    /// The total length of the streams of a footer which fits the schema.
+    fn streams_length(&self, footer: &orc_proto::StripeFooter) -> Option<u64> {
+        if footer.get_columns().len() != self.num_columns || footer.get_streams().is_empty() {
+            return None;
+        }
+        footer.get_streams().iter().try_fold(0u64, |sum, stream| {
+            if (stream.get_column() as usize) < self.num_columns { sum.checked_add(stream.get_length()) } else { None }
+        })
+    }
+
+    /// Returns the stripe if its rows can be decoded.
+    fn check_stripe(
+        &mut self, offset: u64, footer_start: u64, footer: orc_proto::StripeFooter, footer_length: u64,
+    ) -> Result<Option<SalvagedStripe>> {
+        let length = footer_start - offset;
+        let bytes = read_range(&mut self.input, offset, length)?;
+        let num_rows = match count_rows(&bytes, &footer, self.decompression()) {
+            Ok(n) => n,
+            Err(_) => return Ok(None),
+        };
+        if decode_stripe_rows(&bytes, &footer, self.decompression(), &self.schema, num_rows).is_err() {
+            return Ok(None);
+        }
+        Ok(Some(SalvagedStripe { offset, length, footer, footer_length, num_rows }))
+    }
+}
+
+/// A stretch of the input, read ahead so that searching through it does not seek at every offset.
+struct Window {
+    start: u64,
+    bytes: Vec<u8>,
+}
+
+impl Window {
+    /// Returns the input from `offset`: at least `FOOTER_LOOKAHEAD` bytes, unless the input ends 
+    /// first.
+    fn at<R: Read + Seek>(&mut self, input: &mut R, offset: u64, input_len: u64) -> Result<&[u8]> {
+        let end = self.start + self.bytes.len() as u64;
+        if offset < self.start || (end < input_len && end < offset + FOOTER_LOOKAHEAD) {
+            self.start = offset;
+            self.bytes = read_range(input, offset, (4 * FOOTER_LOOKAHEAD).min(input_len - offset))?;
+        }
+        Ok(&self.bytes[(offset - self.start) as usize..])
+    }
+}
+
+fn parse_chunk_header(header: &[u8]) -> (u64, bool) {
+    let header = header[0] as u64 | (header[1] as u64) << 8 | (header[2] as u64) << 16;
+    (header >> 1, header & 1 == 1)
+}
+
+/// Counts the rows of a stripe from the row index of its root column.
+fn count_rows(bytes: &[u8], footer: &orc_proto::StripeFooter, decompression: Decompression) -> Result<u64> {
+    let mut offset: u64 = 0;
+    for stream in footer.get_streams() {
+        let end = offset.checked_add(stream.get_length()).filter(|&end| end <= bytes.len() as u64)
+            .ok_or_else(|| invalid_data("Stream extends past the end of the stripe"))?;
+        if stream.get_column() == 0 && stream.get_kind() == orc_proto::Stream_Kind::ROW_INDEX {
+            let index: orc_proto::RowIndex = parse_message(
+                &decompression.decompress(&bytes[offset as usize..end as usize])?)?;
+            return index.get_entry().iter()
+                .try_fold(0u64, |sum, e| sum.checked_add(e.get_statistics().get_numberOfValues()))
+                .ok_or_else(|| invalid_data("Invalid row count"));
+        }
+        offset = end;
+    }
+    Err(invalid_data("Missing row index for the root column"))
+}
+
+fn count_columns(schema: &Schema, count: &mut usize) {
+    *count += 1;
+    match schema {
+        Schema::Struct(fields) => fields.iter().for_each(|f| count_columns(&f.1, count)),
+        Schema::List(elem) => count_columns(elem, count),
+        Schema::Map(key, value) => {
+            count_columns(key, count);
+            count_columns(value, count);
+        }
+        Schema::Union(variants) => variants.iter().for_each(|v| count_columns(v, count)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+    use crate::reader::Reader;
+    use crate::value::Value;
+    use crate::schema::Field;
+    use crate::writer::Config;
+
+    #[test]
+    fn test_recover() {
+        let path = std::env::temp_dir().join(format!("orc-format-recover-{}.orc", std::process::id()));
+        let schema = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let config = Config::new().with_stripe_row_limit(1000);
+        let side_file = File::create(flush_length_path(&path)).unwrap();
+        let mut writer = Writer::new(File::create(&path).unwrap(), &schema, config).unwrap()
+            .with_flush_lengths(side_file);
+        for i in 0..3500 {
+            writer.write_row(&Value::Struct(vec![Value::Int(i)])).unwrap();
+        }
+        // The process "dies" partway through writing a stripe.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+        drop(writer);
+
+        let len = recover(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+        assert!(!flush_length_path(&path).exists());
+        let mut reader = Reader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.num_stripes(), 3);
+        assert_eq!(reader.rows().unwrap(), (0..3000).map(|i| Value::Struct(vec![Value::Int(i)])).collect::<Vec<_>>());
+
+        // A length that does not end with a tail leaves the file as it is.
+        fs::write(flush_length_path(&path), (len - 10).to_be_bytes()).unwrap();
+        assert_eq!(recover(&path).err().unwrap().kind(), ErrorKind::InvalidData);
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+        fs::remove_file(flush_length_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_salvage() {
+        let schema: Schema = "struct<a:bigint,b:string,c:array<double>>".parse().unwrap();
+        let row = |i: i64| Value::Struct(vec![
+            Value::Int(i),
+            if i % 7 == 0 { Value::Null } else { Value::String(i.to_string()) },
+            Value::List(vec![Value::Double(i as f64); (i % 3) as usize]),
+        ]);
+        let compressions = vec![NoCompression::new().build(), ZstdCompression::new().build(), SnappyCompression::new().build()];
+        for compression in compressions {
+            let config = Config::new().with_compression(compression.clone()).with_stripe_row_limit(1000);
+            let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+            for i in 0..3500 {
+                writer.write_row(&row(i)).unwrap();
+            }
+            let mut out = writer.finish().unwrap();
+            // Lose the tail and part of the last stripes.
+            out.truncate(out.len() * 3 / 4);
+
+            let mut salvage = Salvage::scan(Cursor::new(&out), &schema).unwrap();
+            assert_eq!(salvage.compression().kind(), compression.kind());
+            let num_rows = salvage.num_rows();
+            assert!(num_rows >= 2000 && num_rows % 1000 == 0);
+            assert_eq!(salvage.num_stripes() as u64, num_rows / 1000);
+
+            let config = Config::new().with_compression(salvage.compression());
+            let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+            salvage.write_to(&mut writer).unwrap();
+            let mut reader = Reader::new(Cursor::new(writer.finish().unwrap())).unwrap();
+            assert_eq!(reader.rows().unwrap(), (0..num_rows as i64).map(row).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_salvage_damaged_stripe() {
+        let schema: Schema = "struct<a:bigint,b:string>".parse().unwrap();
+        let row = |i: i64| Value::Struct(vec![Value::Int(i), Value::String(format!("{:x}", i * 7919))]);
+        for compression in vec![NoCompression::new().build(), ZstdCompression::new().with_block_size(1024).build()] {
+            let config = Config::new().with_compression(compression).with_stripe_row_limit(1000);
+            let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+            for i in 0..4000 {
+                writer.write_row(&row(i)).unwrap();
+            }
+            let mut out = writer.finish().unwrap();
+            // Wipe the start of the second stripe, and lose the tail.
+            let tail = FileTail::read(&mut Cursor::new(&out), out.len() as u64).unwrap();
+            let stripes = tail.footer.get_stripes();
+            let start = stripes[1].get_offset() as usize;
+            out[start..start + 20].fill(0);
+            out.truncate(tail.metadata_start as usize);
+
+            let mut salvage = Salvage::scan(Cursor::new(&out), &schema).unwrap();
+            assert_eq!(salvage.num_stripes(), 3);
+            assert_eq!(salvage.salvaged_length(), tail.metadata_start);
+            let mut writer = Writer::new(Vec::new(), &schema, Config::new()).unwrap();
+            salvage.write_to(&mut writer).unwrap();
+            let mut reader = Reader::new(Cursor::new(writer.finish().unwrap())).unwrap();
+            let expected: Vec<Value> = (0..1000).chain(2000..4000).map(row).collect();
+            assert_eq!(reader.rows().unwrap(), expected);
+        }
+    }
+}
@@ -1,7 +1,12 @@
-#[derive(Clone)]
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+use crate::writer::data::DecimalData;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Field(pub String, pub Schema);
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Schema {
     Boolean,
     Short,
@@ -21,3 +26,191 @@ pub enum Schema {
     Map(Box<Schema>, Box<Schema>),
     Union(Vec<Schema>),
 }
+
+impl Schema {
+    /// Checks that the parameters of the schema's types can be written, e.g. that no decimal has a 
+    /// precision over 38 or a scale over its precision.
+    pub(crate) fn check(&self) -> Result<()> {
+        match self {
+            Schema::Decimal(precision, scale) => {
+                if *precision > DecimalData::MAX_PRECISION {
+                    return Err(Error::new(ErrorKind::InvalidInput, 
+                        format!("Decimal precision {} exceeds {}", precision, DecimalData::MAX_PRECISION)));
+                }
+                if scale > precision {
+                    return Err(Error::new(ErrorKind::InvalidInput, 
+                        format!("Decimal scale {} exceeds the precision {}", scale, precision)));
+                }
+                Ok(())
+            }
+            Schema::Struct(fields) => fields.iter().try_for_each(|Field(_, schema)| schema.check()),
+            Schema::List(elem) => elem.check(),
+            Schema::Map(key, value) => key.check().and_then(|_| value.check()),
+            Schema::Union(variants) => variants.iter().try_for_each(Schema::check),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Schema {
+    type Err = Error;
+
+    /// Parses an ORC type description, as used by Hive and the ORC tools, e.g. 
+    /// `struct<id:bigint,tags:array<string>,price:decimal(10,2)>`.
+    fn from_str(s: &str) -> Result<Schema> {
+        let mut parser = Parser { s, pos: 0 };
+        let schema = parser.parse_type()?;
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(parser.error());
+        }
+        Ok(schema)
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::new(ErrorKind::InvalidInput, format!("Invalid type description {:?} at position {}", self.s, self.pos))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.s[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) { Ok(()) } else { Err(self.error()) }
+    }
+
+    /// Reads a type or field name, which extends up to the next delimiter.
+    fn name(&mut self) -> Result<&'a str> {
+        self.skip_whitespace();
+        let rest = &self.s[self.pos..];
+        let len = rest.find(|c: char| "<>(),:".contains(c) || c.is_whitespace()).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error());
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        self.name()?.parse().map_err(|_| self.error())
+    }
+
+    fn length(&mut self) -> Result<u32> {
+        self.expect('(')?;
+        let len = self.number()?;
+        self.expect(')')?;
+        Ok(len)
+    }
+
+    fn parse_type(&mut self) -> Result<Schema> {
+        let start = self.pos;
+        let schema = match self.name()?.to_ascii_lowercase().as_str() {
+            "boolean" => Schema::Boolean,
+            "smallint" => Schema::Short,
+            "int" => Schema::Int,
+            "bigint" => Schema::Long,
+            "date" => Schema::Date,
+            "float" => Schema::Float,
+            "double" => Schema::Double,
+            "timestamp" => Schema::Timestamp,
+            "string" => Schema::String,
+            "binary" => Schema::Binary,
+            "decimal" => {
+                self.expect('(')?;
+                let precision = self.number()?;
+                self.expect(',')?;
+                let scale = self.number()?;
+                self.expect(')')?;
+                Schema::Decimal(precision, scale)
+            }
+            "char" => Schema::Char(self.length()?),
+            "varchar" => Schema::VarChar(self.length()?),
+            "struct" => {
+                self.expect('<')?;
+                let mut fields: Vec<Field> = Vec::new();
+                if !self.eat('>') {
+                    loop {
+                        let name = self.name()?.to_owned();
+                        self.expect(':')?;
+                        fields.push(Field(name, self.parse_type()?));
+                        if self.eat('>') { break; }
+                        self.expect(',')?;
+                    }
+                }
+                Schema::Struct(fields)
+            }
+            "array" => {
+                self.expect('<')?;
+                let elem = self.parse_type()?;
+                self.expect('>')?;
+                Schema::List(Box::new(elem))
+            }
+            "map" => {
+                self.expect('<')?;
+                let key = self.parse_type()?;
+                self.expect(',')?;
+                let value = self.parse_type()?;
+                self.expect('>')?;
+                Schema::Map(Box::new(key), Box::new(value))
+            }
+            "uniontype" => {
+                self.expect('<')?;
+                let mut variants = vec![self.parse_type()?];
+                while self.eat(',') {
+                    variants.push(self.parse_type()?);
+                }
+                self.expect('>')?;
+                Schema::Union(variants)
+            }
+            _ => {
+                self.pos = start;
+                return Err(self.error());
+            }
+        };
+        Ok(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let schema: Schema = "struct<id:bigint, tags:array<string>,price:decimal(10,2),m:map<varchar(5),uniontype<int,double>>>"
+            .parse().unwrap();
+        assert_eq!(schema, Schema::Struct(vec![
+            Field("id".to_owned(), Schema::Long),
+            Field("tags".to_owned(), Schema::List(Box::new(Schema::String))),
+            Field("price".to_owned(), Schema::Decimal(10, 2)),
+            Field("m".to_owned(), Schema::Map(
+                Box::new(Schema::VarChar(5)),
+                Box::new(Schema::Union(vec![Schema::Int, Schema::Double])))),
+        ]));
+        assert!("struct<id:bigint".parse::<Schema>().is_err());
+        assert!("list<int>".parse::<Schema>().is_err());
+        let schema: Schema = "struct<a:decimal(10,12)>".parse().unwrap();
+        assert_eq!(schema.check().err().unwrap().kind(), ErrorKind::InvalidInput);
+        assert!(Schema::Decimal(39, 0).check().is_err());
+        let writer = crate::writer::Writer::new(Vec::new(), &schema, crate::writer::Config::new());
+        assert_eq!(writer.err().unwrap().kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,98 @@
+//! Rewriting ORC files with a different configuration, such as another compression or stripe size.
+//!
+//! Input files are read with this crate's `Reader`, so only files using the DIRECT encoding with 
+//! RLEv1 and NONE, Snappy or Zstd compression can be transcoded. Files using RLEv2 or dictionary 
+//! encodings, or ZLIB, LZO or LZ4 compression, as typically written by Hive and ORC Java, are 
+//! rejected with an `InvalidData` error naming the unsupported compression, or the encoding and 
+//! its column.
+
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+
+use crate::reader::Reader;
+use crate::writer::Writer;
+
+/// Rewrites the ORC file `input` through `writer`, which must have the file's schema, and finishes 
+/// the writer. The file is read one stripe at a time, so that memory use is bounded by the stripe 
+/// size rather than the file size. User metadata is copied. Columns are encoded as the writer 
+/// always encodes them. Returns the number of rows written along with the writer's output.
+pub fn transcode<R: Read + Seek, W: Write>(input: R, mut writer: Writer<W>) -> Result<(u64, W)> {
+    let mut reader = Reader::new(input)?;
+    if reader.schema() != writer.schema() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Writer schema differs from the file's"));
+    }
+    for (name, value) in reader.user_metadata() {
+        writer.add_user_metadata(name, value);
+    }
+    let mut num_rows = 0;
+    for i in 0..reader.num_stripes() {
+        for row in reader.read_stripe(i)? {
+            writer.write_row(&row)?;
+            num_rows += 1;
+        }
+    }
+    Ok((num_rows, writer.finish()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::orc_proto;
+    use crate::reader::read_stripe_footer;
+    use crate::reader::metadata::FileTail;
+    use crate::schema::{Field, Schema};
+    use crate::value::Value;
+    use crate::writer::Config;
+    use crate::writer::compression::ZstdCompression;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_transcode() {
+        let schema = Schema::Struct(vec![
+            Field("a".to_owned(), Schema::Long),
+            Field("b".to_owned(), Schema::List(Box::new(Schema::String))),
+        ]);
+        let rows: Vec<Value> = (0..5000).map(|i| Value::Struct(vec![
+            Value::Int(i),
+            Value::List(vec![Value::String(format!("{}", i * 31)); (i % 3) as usize]),
+        ])).collect();
+        let mut writer = Writer::new(Vec::new(), &schema, Config::new().with_stripe_row_limit(1000)).unwrap();
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        writer.add_user_metadata("origin", b"legacy");
+        let input = writer.finish().unwrap();
+
+        let config = Config::new().with_compression(ZstdCompression::new().build());
+        let writer = Writer::new(Vec::new(), &schema, config).unwrap();
+        let (num_rows, output) = transcode(Cursor::new(&input), writer).unwrap();
+        assert_eq!(num_rows, 5000);
+        assert!(output.len() < input.len());
+        let mut reader = Reader::new(Cursor::new(&output)).unwrap();
+        assert_eq!(reader.num_stripes(), 1);
+        assert_eq!(reader.user_metadata(), vec![("origin", &b"legacy"[..])]);
+        assert_eq!(reader.rows().unwrap(), rows);
+
+        let other = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let writer = Writer::new(Vec::new(), &other, Config::new()).unwrap();
+        assert_eq!(transcode(Cursor::new(&input), writer).err().unwrap().kind(), ErrorKind::InvalidInput);
+
+        // A stripe claiming an encoding the reader does not support is rejected, naming the column.
+        let len = input.len() as u64;
+        let mut input = Cursor::new(&input);
+        let tail = FileTail::read(&mut input, len).unwrap();
+        let metadata = tail.read_metadata(&mut input).unwrap();
+        let info = &tail.footer.get_stripes()[0];
+        let mut footer = read_stripe_footer(&mut input, tail.decompression(), info).unwrap();
+        footer.mut_columns()[3].set_kind(orc_proto::ColumnEncoding_Kind::DICTIONARY_V2);
+        let index_end = info.get_offset() + info.get_indexLength();
+        let mut writer = Writer::new(Vec::new(), &schema, Config::new()).unwrap();
+        writer.write_encoded_stripe(&mut input, &[info.get_offset()..index_end], 
+            &[index_end..index_end + info.get_dataLength()], &footer, info.get_numberOfRows(), 
+            metadata.get_stripeStats()[0].get_colStats()).unwrap();
+        let legacy = writer.finish().unwrap();
+        let writer = Writer::new(Vec::new(), &schema, Config::new()).unwrap();
+        let error = transcode(Cursor::new(&legacy), writer).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().contains("DICTIONARY_V2 for column 3 (b._elem)"), "{}", error);
+    }
+}
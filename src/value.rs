@@ -0,0 +1,49 @@
+/// A dynamically-typed value, used for reading and writing rows whose schema is only known at
+/// runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A value of a `Short`, `Int` or `Long` column
+    Int(i64),
+    /// A value of a `Float` or `Double` column
+    Double(f64),
+    /// An unscaled value together with its scale, e.g. `Decimal(12345, 2)` represents 123.45.
+    Decimal(i128, u32),
+    /// A value of a `String`, `Char` or `VarChar` column
+    String(String),
+    Binary(Vec<u8>),
+    /// Seconds and nanoseconds since the UNIX epoch
+    Timestamp(i64, u32),
+    /// Days since the UNIX epoch
+    Date(i32),
+    List(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Struct(Vec<Value>),
+    /// The tag (index of the variant) and the value
+    Union(u8, Box<Value>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "Null",
+            Value::Bool(_) => "Bool",
+            Value::Int(_) => "Int",
+            Value::Double(_) => "Double",
+            Value::Decimal(_, _) => "Decimal",
+            Value::String(_) => "String",
+            Value::Binary(_) => "Binary",
+            Value::Timestamp(_, _) => "Timestamp",
+            Value::Date(_) => "Date",
+            Value::List(_) => "List",
+            Value::Map(_) => "Map",
+            Value::Struct(_) => "Struct",
+            Value::Union(_, _) => "Union",
+        }
+    }
+}
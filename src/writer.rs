@@ -1,22 +1,41 @@
 use super::protos::orc_proto;
 use super::schema::Schema;
+use super::value::Value;
+use super::record::OrcRecord;
+use super::reader::metadata::{FileTail, read_range};
+use super::memory::{Allocator, MemoryAccount, MemoryCheck, MemoryManager, MemoryRegistration, SystemAllocator};
+use byteorder::{BigEndian, WriteBytesExt};
 use protobuf::{CodedOutputStream, Message, RepeatedField};
-use std::io::{Result, Write};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::ops::Range;
+use std::process;
 use std::slice;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use background::BackgroundFlush;
 use count_write::CountWrite;
-use statistics::{BaseStatistics, Statistics};
-use stripe::{Stripe, StripeInfo};
+use statistics::BaseStatistics;
+use stripe::{Stripe, StripeInfo, write_stripe_footer};
 
-use data::{Data, BaseData};
-use compression::{Compression, CompressionStream, NoCompression};
+use column::{Column, ColumnData};
+use data::{Data, BaseData, RoundingMode};
+use compression::{Compression, CompressionPool, CompressionStream, NoCompression};
 
+pub mod column;
 pub mod compression;
 pub mod data;
+pub mod partitioned;
+pub mod rolling;
 mod stripe;
-mod encoder;
+pub(crate) mod encoder;
 mod statistics;
 mod count_write;
+mod background;
+
+pub use statistics::Statistics;
 
 
 #[derive(Clone)]
@@ -24,6 +43,14 @@ pub struct Config {
     row_index_stride: u32,
     compression: Compression,
     stripe_size: usize,
+    stripe_row_limit: Option<u64>,
+    block_padding: Option<u64>,
+    range_checks: bool,
+    rounding_mode: RoundingMode,
+    allocator: Arc<dyn Allocator>,
+    memory_limit: Option<usize>,
+    memory_manager: Option<Arc<MemoryManager>>,
+    compression_pool: Option<Arc<CompressionPool>>,
 }
 
 impl Config {
@@ -32,6 +59,14 @@ impl Config {
             row_index_stride: 10000,
             compression: NoCompression::new().build(),
             stripe_size: 67108864,
+            stripe_row_limit: None,
+            block_padding: None,
+            range_checks: false,
+            rounding_mode: RoundingMode::HalfUp,
+            allocator: Arc::new(SystemAllocator),
+            memory_limit: None,
+            memory_manager: None,
+            compression_pool: None,
         }
     }
 
@@ -49,72 +84,534 @@ impl Config {
         self.stripe_size = stripe_size;
         self
     }
+
+    /// Limits the number of rows in a stripe: a stripe is flushed once it reaches either 
+    /// `stripe_row_limit` rows or the stripe size, whichever comes first.
+    pub fn with_stripe_row_limit(mut self, stripe_row_limit: u64) -> Self {
+        self.stripe_row_limit = Some(stripe_row_limit);
+        self
+    }
+
+    /// Keeps stripes from crossing boundaries between file system blocks of `block_size` bytes 
+    /// (like ORC Java's `orc.block.padding`). When the space left in the current block is a 
+    /// reasonable fraction of the stripe size, the next stripe is shrunk to fit it; otherwise, or 
+    /// if the stripe still does not fit, the rest of the block is padded with zeros. Stripes larger 
+    /// than a block are written without padding. While flushing in the background, stripes are
+    /// still padded but not shrunk, as their offsets are not known in advance. Stripes copied 
+    /// with `Writer::copy_stripes` are not padded.
+    pub fn with_block_padding(mut self, block_size: u64) -> Self {
+        assert!(block_size > 0, "block_size must be positive");
+        self.block_padding = Some(block_size);
+        self
+    }
+
+    /// When enabled, plain `write` calls on narrower column types (e.g. `Short`, `Int`, `Decimal`) 
+    /// panic on values that do not fit the column's type, rather than silently storing them.
+    pub fn with_range_checks(mut self, range_checks: bool) -> Self {
+        self.range_checks = range_checks;
+        self
+    }
+
+    /// Sets how decimal values with more fractional digits than the column's scale are rounded.
+    pub fn with_rounding_mode(mut self, rounding_mode: RoundingMode) -> Self {
+        self.rounding_mode = rounding_mode;
+        self
+    }
+
+    /// Sets the source of the buffers in which stripes are accumulated, e.g. a `BufferPool` shared 
+    /// between writers.
+    pub fn with_allocator(mut self, allocator: Arc<dyn Allocator>) -> Self {
+        self.allocator = allocator;
+        self
+    }
+
+    /// Sets a limit on the buffer memory held by each writer. When it is reached, the current stripe 
+    /// is flushed early (at the end of the batch) and the buffers are released.
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
+
+    /// Registers each writer created with this config with a `MemoryManager` shared with other 
+    /// writers, which may scale down the stripe size and request early flushes.
+    pub fn with_memory_manager(mut self, memory_manager: Arc<MemoryManager>) -> Self {
+        self.memory_manager = Some(memory_manager);
+        self
+    }
+
+    /// Compresses blocks on the threads of the given pool, so that encoding and compression overlap.
+    /// Stripes are encoded identically to compressing on the writer's own thread, although stripe
+    /// boundaries may differ slightly, as blocks still being compressed count at their uncompressed
+    /// size. Has no effect without compression.
+    pub fn with_compression_pool(mut self, pool: Arc<CompressionPool>) -> Self {
+        self.compression_pool = Some(pool);
+        self
+    }
+}
+
+/// An output written under a temporary name, which replaces `path` once complete.
+struct TempFile<W> {
+    temp_path: PathBuf,
+    path: PathBuf,
+    // Makes the written data durable before the rename
+    sync: fn(&W) -> Result<()>,
 }
 
 #[must_use]
 pub struct Writer<W: Write> {
-    inner: CountWrite<W>,
+    // None while the output is owned by the background flush
+    inner: Option<CountWrite<W>>,
+    background: Option<BackgroundFlush<W>>,
+    flush_lengths: Option<Box<dyn Write + Send>>,
+    temp_file: Option<TempFile<W>>,
+    schema: Schema,
     config: Config,
+    user_metadata: Vec<(String, Vec<u8>)>,
     current_stripe: Stripe,
     stripe_infos: Vec<StripeInfo>,
+    memory: Arc<MemoryAccount>,
+    memory_registration: Option<MemoryRegistration>,
+    rows_since_memory_check: u64,
 }
 
 impl<W: Write> Writer<W> {
     const HEADER_LENGTH: u64 = 3;
+    // The fraction of the stripe size below which the rest of a block is padded rather than 
+    // filled with a smaller stripe (as ORC Java's default `orc.block.padding.tolerance`)
+    const PADDING_TOLERANCE: f64 = 0.05;
 
     pub fn new(inner: W, schema: &Schema, config: Config) -> Result<Self> {
-        let mut writer = Self {
-            inner: CountWrite::new(inner),
+        let mut writer = Self::with_output(CountWrite::new(inner), schema, config)?;
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    /// Makes a writer to `out`, returning an `InvalidInput` error if `schema` cannot be written.
+    fn with_output(out: CountWrite<W>, schema: &Schema, mut config: Config) -> Result<Self> {
+        schema.check()?;
+        let memory = Arc::new(MemoryAccount::new(config.allocator.clone(), config.memory_limit));
+        config.compression = config.compression.with_memory(&memory);
+        if let Some(pool) = &config.compression_pool {
+            config.compression = config.compression.with_pool(pool);
+        }
+        let memory_registration = config.memory_manager.as_ref().map(|m| m.register(config.stripe_size));
+        Ok(Self {
+            inner: Some(out),
+            background: None,
+            flush_lengths: None,
+            temp_file: None,
+            schema: schema.clone(),
             current_stripe: Stripe::new(schema, &config),
             config,
+            user_metadata: Vec::new(),
             stripe_infos: Vec::new(),
-        };
-        writer.write_header()?;
-        Ok(writer)
+            memory,
+            memory_registration,
+            rows_since_memory_check: 0,
+        })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Adds a named value to the file's user metadata, replacing any earlier value of that name.
+    pub fn add_user_metadata(&mut self, name: &str, value: &[u8]) {
+        self.user_metadata.retain(|(n, _)| n != name);
+        self.user_metadata.push((name.to_owned(), value.to_vec()));
     }
 
     pub fn data(&mut self) -> &mut Data {
         &mut self.current_stripe.data
     }
 
+    /// Resolves a typed handle to the column with the given dot-separated name (e.g. `"user.id"`),
+    /// returning an error if there is no such column or it is not of type `T`. List elements are
+    /// named `_elem`, map keys and values `_key` and `_value`, and union variants by their index.
+    pub fn column<T: ColumnData>(&self, name: &str) -> Result<Column<T>> {
+        Column::resolve(&self.current_stripe.data, name)
+    }
+
+    /// Returns the column referred to by a handle obtained from `column`, looking it up along the
+    /// handle's path on each call; to write many values, hold on to the returned reference. Returns
+    /// an error if the handle was resolved against a different schema.
+    pub fn get<T: ColumnData>(&mut self, column: &Column<T>) -> Result<&mut T> {
+        column.get(&mut self.current_stripe.data)
+    }
+
+    /// Number of bytes of buffer memory currently held by the writer.
+    pub fn memory_usage(&self) -> usize {
+        self.memory.used()
+    }
+
+    /// Number of bytes written to the output so far. While flushing in the background, stripes 
+    /// count once the background thread has written them, not when they are queued.
+    pub fn bytes_written(&self) -> u64 {
+        match &self.background {
+            Some(background) => background.written(),
+            None => self.inner.as_ref().unwrap().pos() as u64,
+        }
+    }
+
+    /// Returns the output. Panics if stripes are being flushed in the background.
     pub fn inner(&self) -> &W {
-        &self.inner.inner
+        &self.inner.as_ref().expect("The output is owned by the background flush").inner
     }
 
     pub fn write_batch(&mut self, num_rows: u64) -> Result<()> {
         self.current_stripe.write_batch(num_rows)?;
-        if self.current_stripe.data.estimated_size() > self.config.stripe_size {
-            self.current_stripe.finish(&mut self.inner, &mut self.stripe_infos)?;
+        let stripe_size = self.current_stripe.data.estimated_size();
+        let row_limit_reached = match self.config.stripe_row_limit {
+            Some(limit) => self.current_stripe.num_rows >= limit,
+            None => false,
+        };
+        let mut check = if stripe_size > self.target_stripe_size() || row_limit_reached || self.memory.is_over_limit() {
+            MemoryCheck::FlushStripe
+        } else {
+            MemoryCheck::Continue
+        };
+        if let Some(registration) = &self.memory_registration {
+            self.rows_since_memory_check += num_rows;
+            if check == MemoryCheck::Continue && self.rows_since_memory_check >= MemoryManager::ROWS_BETWEEN_CHECKS {
+                self.rows_since_memory_check = 0;
+                check = registration.check(stripe_size, self.memory.used());
+            }
+        }
+        let release_memory = match check {
+            MemoryCheck::Continue => return Ok(()),
+            MemoryCheck::FlushStripe => false,
+            MemoryCheck::FlushAndRelease => true,
+        };
+        self.flush_stripe(release_memory)?;
+        if self.flush_lengths.is_some() {
+            self.append_tail()?;
         }
         Ok(())
     }
 
-    pub fn finish(mut self) -> Result<W> {
+    /// The size at which to flush the current stripe, which is shrunk to fit the space left in 
+    /// the current block when padding to block boundaries.
+    fn target_stripe_size(&self) -> usize {
+        match self.config.block_padding {
+            Some(block_size) if self.background.is_none() => {
+                let available = (block_size - self.current_stripe.offset % block_size) as usize;
+                let stripe_size = self.config.stripe_size;
+                if available < stripe_size && available as f64 > Self::PADDING_TOLERANCE * stripe_size as f64 {
+                    available
+                } else {
+                    stripe_size
+                }
+            }
+            _ => self.config.stripe_size,
+        }
+    }
+
+    fn flush_stripe(&mut self, release_memory: bool) -> Result<()> {
+        if let Some(background) = &mut self.background {
+            background.collect(&mut self.stripe_infos);
+            let next = match background.take_spare() {
+                Some(stripe) => stripe,
+                None => Stripe::new(&self.schema, &self.config),
+            };
+            let stripe = std::mem::replace(&mut self.current_stripe, next);
+            if !background.submit(stripe, release_memory) {
+                return Err(self.background_error());
+            }
+        } else {
+            self.memory.set_releasing(release_memory);
+            let result = self.current_stripe.finish(self.inner.as_mut().unwrap(), &mut self.stripe_infos);
+            self.memory.set_releasing(false);
+            result?;
+        }
+        if let Some(registration) = &self.memory_registration {
+            registration.report(self.memory.used());
+        }
+        Ok(())
+    }
+
+    /// Makes the output recoverable if the writer does not get to finish, in the manner of Hive ACID 
+    /// streaming: each stripe is followed by an intermediate footer (see 
+    /// `write_intermediate_footer`), after which the length of the output is appended to 
+    /// `flush_lengths` as a big-endian u64. That side file would usually be created at 
+    /// `recovery::flush_length_path`, so that `recovery::recover` can restore the output to its 
+    /// last recorded length.
+    pub fn with_flush_lengths<F: Write + Send + 'static>(mut self, flush_lengths: F) -> Self {
+        self.flush_lengths = Some(Box::new(flush_lengths));
+        self
+    }
+
+    /// Ends the background flush after it has stopped, returning the error that stopped it.
+    fn background_error(&mut self) -> Error {
+        let background = self.background.take().unwrap();
+        match background.finish(&mut self.stripe_infos) {
+            Ok(_) => Error::other("Background flush stopped unexpectedly"),
+            Err(e) => e,
+        }
+    }
+
+    /// Writes a single row, given as a dynamically-typed value. If the value does not match the 
+    /// schema, an error is returned and nothing is written.
+    pub fn write_row(&mut self, row: &Value) -> Result<()> {
+        let data = &mut self.current_stripe.data;
+        data.check_value(row)?;
+        data.write_value(row)?;
+        self.write_batch(1)
+    }
+
+    /// Writes a single row from a struct implementing `OrcRecord` (usually via
+    /// `#[derive(OrcRecord)]`). The writer's schema must be `T::schema()`.
+    pub fn write_record<T: OrcRecord>(&mut self, record: &T) -> Result<()> {
+        record.write_to(self.current_stripe.data.unwrap_struct());
+        self.write_batch(1)
+    }
+
+    /// Flushes the current stripe and appends a complete file tail (metadata, footer and 
+    /// postscript) describing all stripes written so far, so that the output up to this point can 
+    /// be read as an ORC file. Returns the length of that file. Writing may continue afterwards; 
+    /// later stripes and tails are written after this tail.
+    pub fn write_intermediate_footer(&mut self) -> Result<u64> {
+        self.flush_stripe(false)?;
+        self.append_tail()
+    }
+
+    /// Appends a file tail after the stripes flushed so far, recording the resulting length if 
+    /// flush lengths are tracked.
+    fn append_tail(&mut self) -> Result<u64> {
+        let mut out = self.take_output()?;
+        // A background flush leaves the current stripe with a stale offset.
+        self.current_stripe.offset = out.pos() as u64;
+        let result = self.write_tail(&mut out);
+        let len = out.pos() as u64;
+        self.current_stripe.offset = len;
+        self.restore_output(out);
+        result?;
+        self.record_flush_length(len)?;
+        Ok(len)
+    }
+
+    /// Takes the output to write to it directly, once all stripes flushed so far are written. It 
+    /// must be given back with `restore_output`.
+    fn take_output(&mut self) -> Result<CountWrite<W>> {
+        match &mut self.background {
+            Some(background) => match background.lend_output(&mut self.stripe_infos) {
+                Some(out) => Ok(out),
+                None => Err(self.background_error()),
+            },
+            None => Ok(self.inner.take().unwrap()),
+        }
+    }
+
+    fn restore_output(&mut self, out: CountWrite<W>) {
+        match &mut self.background {
+            Some(background) => background.return_output(out),
+            None => self.inner = Some(out),
+        }
+    }
+
+    /// Copies the stripes of the ORC file `input` to the output as they are, without decoding 
+    /// them, after flushing the rows written so far. The file must have the writer's schema, 
+    /// compression and row index stride. Its stripe statistics are kept, and merged into the file 
+    /// statistics. Returns the number of rows copied. The copied stripes are not padded to block 
+    /// boundaries.
+    pub fn copy_stripes<R: Read + Seek>(&mut self, mut input: R) -> Result<u64> {
+        let file_len = input.seek(SeekFrom::End(0))?;
+        let tail = FileTail::read(&mut input, file_len)?;
+        if tail.schema()? != self.schema {
+            return Err(Error::new(ErrorKind::InvalidInput, "File was written with a different schema"));
+        }
+        self.check_compression(&tail.postscript)?;
+        if tail.footer.get_rowIndexStride() != self.config.row_index_stride {
+            return Err(Error::new(ErrorKind::InvalidInput, "File was written with a different row index stride"));
+        }
+        let stripe_infos = Self::read_stripe_infos(&mut input, &tail, &self.current_stripe.data)?;
+
+        self.flush_stripe(false)?;
+        let mut out = self.take_output()?;
+        let mut num_rows = 0;
+        let mut result = Ok(());
+        for mut stripe_info in stripe_infos {
+            let len = stripe_info.index_length + stripe_info.data_length + stripe_info.footer_length;
+            let offset = out.pos() as u64;
+            result = input.seek(SeekFrom::Start(stripe_info.offset))
+                .and_then(|_| io::copy(&mut (&mut input).take(len), &mut out))
+                .and_then(|copied| if copied == len { Ok(()) } else { 
+                    Err(Error::new(ErrorKind::UnexpectedEof, "File ends within a stripe")) 
+                });
+            if result.is_err() {
+                break;
+            }
+            stripe_info.offset = offset;
+            num_rows += stripe_info.num_rows;
+            self.stripe_infos.push(stripe_info);
+        }
+        self.current_stripe.offset = out.pos() as u64;
+        self.restore_output(out);
+        result?;
+        if self.flush_lengths.is_some() {
+            self.append_tail()?;
+        }
+        Ok(num_rows)
+    }
+
+    /// Writes a stripe whose streams are already encoded, by copying its index and data streams 
+    /// from the given ranges of `input`, after flushing the rows written so far. The stripe is not 
+    /// padded to block boundaries.
+    pub(crate) fn write_encoded_stripe<R: Read + Seek>(
+        &mut self, input: &mut R, index_ranges: &[Range<u64>], data_ranges: &[Range<u64>], 
+        footer: &orc_proto::StripeFooter, num_rows: u64, statistics: &[orc_proto::ColumnStatistics],
+    ) -> Result<()> {
+        let mut like: Vec<Statistics> = Vec::new();
+        self.current_stripe.data.statistics(&mut like);
+        if statistics.len() != like.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid stripe statistics"));
+        }
+        let statistics = statistics.iter().zip(&like).map(|(s, l)| Statistics::from_proto(s, l)).collect();
+
+        self.flush_stripe(false)?;
+        let mut out = self.take_output()?;
+        let offset = out.pos() as u64;
+        let mut copy = |out: &mut CountWrite<W>, ranges: &[Range<u64>]| -> Result<u64> {
+            for range in ranges {
+                out.write_all(&read_range(input, range.start, range.end - range.start)?)?;
+            }
+            Ok(ranges.iter().map(|r| r.end - r.start).sum())
+        };
+        let result = copy(&mut out, index_ranges).and_then(|index_length| {
+            let data_length = copy(&mut out, data_ranges)?;
+            let footer_start = out.pos() as u64;
+            write_stripe_footer(&mut out, footer, &self.config.compression)?;
+            Ok(StripeInfo {
+                offset,
+                num_rows,
+                index_length,
+                data_length,
+                footer_length: out.pos() as u64 - footer_start,
+                statistics,
+            })
+        });
+        self.current_stripe.offset = out.pos() as u64;
+        self.restore_output(out);
+        self.stripe_infos.push(result?);
+        if self.flush_lengths.is_some() {
+            self.append_tail()?;
+        }
+        Ok(())
+    }
+
+    fn check_compression(&self, postscript: &orc_proto::PostScript) -> Result<()> {
+        let kind = postscript.get_compression();
+        if kind != self.config.compression.kind() || (kind != orc_proto::CompressionKind::NONE 
+            && postscript.get_compressionBlockSize() != self.config.compression.block_size() as u64) {
+            return Err(Error::new(ErrorKind::InvalidInput, "File was written with a different compression"));
+        }
+        Ok(())
+    }
+
+    /// Reads the information and statistics of the stripes of a file with the schema of `data`.
+    fn read_stripe_infos<R: Read + Seek>(input: &mut R, tail: &FileTail, data: &Data) -> Result<Vec<StripeInfo>> {
+        let metadata = tail.read_metadata(input)?;
+        let stripes = tail.footer.get_stripes();
+        let stripe_stats = metadata.get_stripeStats();
+        if stripe_stats.len() != stripes.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "Missing stripe statistics"));
+        }
+        let mut like: Vec<Statistics> = Vec::new();
+        data.statistics(&mut like);
+        let mut stripe_infos: Vec<StripeInfo> = Vec::new();
+        for (stripe, stats) in stripes.iter().zip(stripe_stats) {
+            let col_stats = stats.get_colStats();
+            if col_stats.len() != like.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "Invalid stripe statistics"));
+            }
+            stripe_infos.push(StripeInfo {
+                offset: stripe.get_offset(),
+                num_rows: stripe.get_numberOfRows(),
+                index_length: stripe.get_indexLength(),
+                data_length: stripe.get_dataLength(),
+                footer_length: stripe.get_footerLength(),
+                statistics: col_stats.iter().zip(&like).map(|(s, l)| Statistics::from_proto(s, l)).collect(),
+            });
+        }
+        Ok(stripe_infos)
+    }
+
+    fn record_flush_length(&mut self, len: u64) -> Result<()> {
+        if let Some(flush_lengths) = &mut self.flush_lengths {
+            flush_lengths.write_u64::<BigEndian>(len)?;
+            flush_lengths.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<W> {
+        self.finish_with_statistics().map(|(out, _)| out)
+    }
+
+    /// Like `finish`, but also returns the statistics of the file's columns, indexed by column id.
+    pub fn finish_with_statistics(mut self) -> Result<(W, Vec<Statistics>)> {
+        let mut out = match self.background.take() {
+            Some(background) => {
+                let out = background.finish(&mut self.stripe_infos)?;
+                self.current_stripe.offset = out.pos() as u64;
+                out
+            }
+            None => self.inner.take().unwrap(),
+        };
         self.current_stripe
-            .finish(&mut self.inner, &mut self.stripe_infos)?;
+            .finish(&mut out, &mut self.stripe_infos)?;
+        self.write_tail(&mut out)?;
+        self.record_flush_length(out.pos() as u64)?;
+        let statistics = self.merge_statistics();
+        let out = out.into_inner();
+        if let Some(temp_file) = self.temp_file.take() {
+            (temp_file.sync)(&out)?;
+            fs::rename(&temp_file.temp_path, &temp_file.path)?;
+        }
+        Ok((out, statistics))
+    }
+
+    /// Abandons the output. For a writer from `create` or `append`, the temporary file is removed, 
+    /// and the destination path is left as it was; other outputs are simply dropped. Dropping a 
+    /// writer without calling `finish` has the same effect, except that errors are ignored.
+    pub fn abort(mut self) -> Result<()> {
+        self.abort_output()
+    }
+
+    fn abort_output(&mut self) -> Result<()> {
+        if let Some(background) = self.background.take() {
+            // Only the thread's exit is awaited; write errors no longer matter.
+            let _ = background.finish(&mut self.stripe_infos);
+        }
+        self.inner.take();
+        match self.temp_file.take() {
+            Some(temp_file) => fs::remove_file(&temp_file.temp_path),
+            None => Ok(()),
+        }
+    }
+
+    fn write_tail(&self, out: &mut CountWrite<W>) -> Result<()> {
         let content_length = self.current_stripe.offset - Self::HEADER_LENGTH;
 
-        let metadata_start_pos = self.inner.pos();
-        self.write_metadata()?;
+        let metadata_start_pos = out.pos();
+        self.write_metadata(out)?;
 
-        let footer_start_pos = self.inner.pos();
-        self.write_footer(content_length)?;
+        let footer_start_pos = out.pos();
+        self.write_footer(out, content_length)?;
 
-        let postscript_start_pos = self.inner.pos();
+        let postscript_start_pos = out.pos();
         let metadata_length = (footer_start_pos - metadata_start_pos) as u64;
         let footer_length = (postscript_start_pos - footer_start_pos) as u64;
-        self.write_postscript(metadata_length, footer_length)?;
+        self.write_postscript(out, metadata_length, footer_length)?;
 
-        let end_pos = self.inner.pos();
+        let end_pos = out.pos();
         let postscript_length = (end_pos - postscript_start_pos) as u8;
-        self.inner.write(slice::from_ref(&postscript_length))?;
+        out.write(slice::from_ref(&postscript_length))?;
 
-        self.inner.flush()?;
-        Ok(self.inner.into_inner())
+        out.flush()
     }
 
     fn write_header(&mut self) -> Result<()> {
-        self.inner.write(b"ORC")?;
+        self.inner.as_mut().unwrap().write(b"ORC")?;
         Ok(())
     }
 
@@ -129,7 +626,7 @@ impl<W: Write> Writer<W> {
         statistics
     }
 
-    fn write_metadata(&mut self) -> Result<()> {
+    fn write_metadata(&self, out: &mut CountWrite<W>) -> Result<()> {
         let mut compression_stream = CompressionStream::new(&self.config.compression);
         let mut coded_out = CodedOutputStream::new(&mut compression_stream);
         let mut metadata = orc_proto::Metadata::new();
@@ -146,7 +643,7 @@ impl<W: Write> Writer<W> {
         metadata.set_stripeStats(RepeatedField::from_vec(stripe_statistics));
         metadata.write_to(&mut coded_out)?;
         coded_out.flush()?;
-        compression_stream.finish(&mut self.inner)?;
+        compression_stream.finish(out)?;
         Ok(())
     }
 
@@ -253,7 +750,7 @@ impl<W: Write> Writer<W> {
         }
     }
 
-    fn write_footer(&mut self, content_length: u64) -> Result<()> {
+    fn write_footer(&self, out: &mut CountWrite<W>, content_length: u64) -> Result<()> {
         let mut compression_stream = CompressionStream::new(&self.config.compression);
         let stats: Vec<_> = self
             .merge_statistics()
@@ -281,19 +778,26 @@ impl<W: Write> Writer<W> {
         Self::make_types(&self.current_stripe.data, &mut types);
         footer.set_types(RepeatedField::from_vec(types));
 
-        footer.set_metadata(RepeatedField::from_vec(vec![]));
+        let mut user_metadata: Vec<orc_proto::UserMetadataItem> = Vec::new();
+        for (name, value) in &self.user_metadata {
+            let mut item = orc_proto::UserMetadataItem::new();
+            item.set_name(name.clone());
+            item.set_value(value.clone());
+            user_metadata.push(item);
+        }
+        footer.set_metadata(RepeatedField::from_vec(user_metadata));
         footer.set_numberOfRows(self.stripe_infos.iter().map(|x| x.num_rows).sum());
         footer.set_statistics(RepeatedField::from_vec(stats));
         footer.set_rowIndexStride(self.config.row_index_stride);
 
         footer.write_to(&mut coded_out)?;
         coded_out.flush()?;
-        compression_stream.finish(&mut self.inner)?;
+        compression_stream.finish(out)?;
         Ok(())
     }
 
-    fn write_postscript(&mut self, metadata_length: u64, footer_length: u64) -> Result<()> {
-        let mut coded_out = CodedOutputStream::new(&mut self.inner);
+    fn write_postscript(&self, out: &mut CountWrite<W>, metadata_length: u64, footer_length: u64) -> Result<()> {
+        let mut coded_out = CodedOutputStream::new(out);
         let mut postscript = orc_proto::PostScript::new();
         postscript.set_compression(self.config.compression.kind());
         postscript.set_compressionBlockSize(self.config.compression.block_size() as u64);
@@ -307,3 +811,416 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 }
+
+impl<W: Write> Drop for Writer<W> {
+    fn drop(&mut self) {
+        let _ = self.abort_output();
+    }
+}
+
+impl Writer<BufWriter<File>> {
+    /// Creates a writer to the file at `path`. The file is written under a temporary name in the 
+    /// same directory, and renamed to `path` by `finish`, so that `path` only ever holds a 
+    /// complete file. If the writer is aborted or dropped instead, the temporary file is removed.
+    pub fn create<P: AsRef<Path>>(path: P, schema: &Schema, config: Config) -> Result<Self> {
+        Self::with_temp_file(path.as_ref(), |file| Writer::new(BufWriter::new(file), schema, config))
+    }
+
+    /// Opens the ORC file at `path` to write more stripes after its existing ones. The file must 
+    /// have been written with `schema` and the compression of `config`; its row index stride is 
+    /// used instead of the configured one, and its user metadata is kept. As with `create`, the 
+    /// result is written under a temporary name, starting with a copy of the existing stripes, and 
+    /// replaces the file once `finish` has written a tail covering both the existing and the new 
+    /// stripes. Until then, and if the writer is aborted, dropped or the process dies, the file is 
+    /// left as it was.
+    pub fn append<P: AsRef<Path>>(path: P, schema: &Schema, mut config: Config) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let tail = FileTail::read(&mut file, file_len)?;
+        if &tail.schema()? != schema {
+            return Err(Error::new(ErrorKind::InvalidInput, "File was written with a different schema"));
+        }
+        config.row_index_stride = tail.footer.get_rowIndexStride();
+        let length = tail.footer.get_stripes().last()
+            .map_or(Self::HEADER_LENGTH, |s| s.get_offset() + s.get_indexLength() + s.get_dataLength() + s.get_footerLength());
+        if length > tail.metadata_start {
+            return Err(Error::new(ErrorKind::InvalidData, "Stripes overlap the file tail"));
+        }
+
+        Self::with_temp_file(path, |mut temp| {
+            temp.set_permissions(file.metadata()?.permissions())?;
+            file.seek(SeekFrom::Start(0))?;
+            io::copy(&mut (&mut file).take(length), &mut temp)?;
+            let mut out = CountWrite::new(BufWriter::new(temp));
+            out.count = length as usize;
+            let mut writer = Writer::with_output(out, schema, config)?;
+            writer.current_stripe.offset = length;
+            writer.check_compression(&tail.postscript)?;
+            for item in tail.footer.get_metadata() {
+                writer.add_user_metadata(item.get_name(), item.get_value());
+            }
+            writer.stripe_infos = Self::read_stripe_infos(&mut file, &tail, &writer.current_stripe.data)?;
+            Ok(writer)
+        })
+    }
+
+    /// Makes a writer with `init` to a temporary file next to `path`, which replaces `path` once 
+    /// the writer is finished. The temporary file is removed if `init` fails.
+    fn with_temp_file<F: FnOnce(File) -> Result<Self>>(path: &Path, init: F) -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = path.file_name()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Output path has no file name"))?;
+        // A leading dot hides the file from Hadoop and Hive while it is being written.
+        let temp_path = path.with_file_name(format!(".{}.{}-{}.tmp", 
+            name.to_string_lossy(), process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let file = File::create(&temp_path)?;
+        let mut writer = match init(file) {
+            Ok(writer) => writer,
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                return Err(e);
+            }
+        };
+        writer.temp_file = Some(TempFile {
+            temp_path,
+            path: path.to_owned(),
+            sync: |out| out.get_ref().sync_all(),
+        });
+        Ok(writer)
+    }
+}
+
+impl<W: Write + Send + 'static> Writer<W> {
+    /// Hands completed stripes to a background thread, which writes them to the output while rows 
+    /// are written into a fresh stripe. Once `max_pending` stripes are waiting to be written, 
+    /// `write_batch` blocks until one of them is done. An error while writing is returned by a 
+    /// later `write_batch` or by `finish`.
+    pub fn with_background_flush(mut self, max_pending: usize) -> Self {
+        if let Some(out) = self.inner.take() {
+            self.background = Some(BackgroundFlush::start(out, self.memory.clone(), max_pending));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::BufferPool;
+    use crate::reader::Reader;
+    use crate::schema::Field;
+    use crate::writer::compression::ZstdCompression;
+    use std::io::Cursor;
+
+    fn write_rows(config: Config, num_rows: i64) -> Writer<Vec<u8>> {
+        let schema = Schema::Struct(vec![
+            Field("a".to_owned(), Schema::Long),
+            Field("b".to_owned(), Schema::String),
+        ]);
+        let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+        for i in 0..num_rows {
+            let row = Value::Struct(vec![Value::Int(i * 7919), Value::String(format!("{:x}", i * 104729))]);
+            writer.write_row(&row).unwrap();
+        }
+        writer
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let compression = ZstdCompression::new().with_block_size(4096).build();
+        let writer = write_rows(Config::new().with_compression(compression.clone()), 20000);
+        assert_eq!(writer.stripe_infos.len(), 0);
+        let unlimited = writer.memory_usage();
+
+        let limit = unlimited / 4;
+        let writer = write_rows(Config::new().with_compression(compression).with_memory_limit(limit), 20000);
+        assert!(writer.stripe_infos.len() > 1);
+        // Usage may exceed the limit only by the growth during the last batch.
+        assert!(writer.memory_usage() < 2 * limit);
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_memory_manager() {
+        let manager = Arc::new(MemoryManager::new(100000));
+        let config = Config::new().with_stripe_size(1000000).with_memory_manager(manager.clone());
+        let writer1 = write_rows(config.clone(), 30000);
+        let writer2 = write_rows(config, 30000);
+        assert_eq!(manager.num_writers(), 2);
+        // With the stripe size scaled down to 50000 bytes, stripes are flushed every 5000 rows.
+        assert_eq!(manager.allocation_scale(), 0.05);
+        assert!(writer2.stripe_infos.len() >= 5);
+        writer1.finish().unwrap();
+        writer2.finish().unwrap();
+        assert_eq!(manager.num_writers(), 0);
+        assert_eq!(manager.current_usage(), 0);
+    }
+
+    #[test]
+    fn test_shared_buffer_pool() {
+        let pool = Arc::new(BufferPool::new(1 << 24));
+        let config = Config::new().with_allocator(pool.clone());
+        let writer = write_rows(config.clone(), 1000);
+        let usage = writer.memory_usage();
+        assert!(usage > 0);
+        writer.finish().unwrap();
+        assert!(pool.pooled_bytes() >= usage);
+
+        // A second writer takes its buffers from those released by the first one.
+        let pooled = pool.pooled_bytes();
+        let writer = write_rows(config, 1000);
+        assert!(pool.pooled_bytes() < pooled);
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_compression_pool() {
+        let compression = ZstdCompression::new().with_block_size(1024).build();
+        let config = Config::new().with_compression(compression).with_row_index_stride(1000);
+        let expected = write_rows(config.clone(), 50000).finish().unwrap();
+        let pool = Arc::new(CompressionPool::new(4));
+        let out = write_rows(config.with_compression_pool(pool), 50000).finish().unwrap();
+        assert!(out == expected);
+    }
+
+    #[test]
+    fn test_background_flush() {
+        let compression = ZstdCompression::new().build();
+        let config = Config::new().with_compression(compression).with_stripe_size(20000);
+        let expected = write_rows(config.clone(), 50000).finish().unwrap();
+        let schema = Schema::Struct(vec![
+            Field("a".to_owned(), Schema::Long),
+            Field("b".to_owned(), Schema::String),
+        ]);
+        let mut writer = Writer::new(Vec::new(), &schema, config).unwrap().with_background_flush(2);
+        for i in 0..50000 {
+            let row = Value::Struct(vec![Value::Int(i * 7919), Value::String(format!("{:x}", i * 104729))]);
+            writer.write_row(&row).unwrap();
+        }
+        let out = writer.finish().unwrap();
+        assert!(out == expected);
+    }
+
+    #[test]
+    fn test_stripe_size_estimate() {
+        let compression = ZstdCompression::new().with_block_size(4096).build();
+        let config = Config::new().with_compression(compression).with_stripe_size(100000);
+        let writer = write_rows(config.clone(), 200000);
+        // Apart from the last, stripes come out close to the configured size.
+        for si in &writer.stripe_infos {
+            let size = si.index_length + si.data_length + si.footer_length;
+            assert!(size > 90000 && size < 110000, "stripe size {}", size);
+        }
+        writer.finish().unwrap();
+
+        let writer = write_rows(config.with_stripe_row_limit(3000), 10000);
+        assert_eq!(writer.stripe_infos.iter().map(|si| si.num_rows).collect::<Vec<_>>(), vec![3000, 3000, 3000]);
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_block_padding() {
+        let block_size = 120000;
+        let config = Config::new().with_stripe_size(50000).with_block_padding(block_size);
+        let writer = write_rows(config, 100000);
+        let mut padded = false;
+        for si in &writer.stripe_infos {
+            let end = si.offset + si.index_length + si.data_length + si.footer_length;
+            assert_eq!(si.offset / block_size, (end - 1) / block_size);
+            padded |= si.offset % block_size == 0;
+        }
+        assert!(padded);
+        let out = writer.finish().unwrap();
+
+        let mut reader = Reader::new(std::io::Cursor::new(out)).unwrap();
+        let rows = reader.rows().unwrap();
+        assert_eq!(rows.len(), 100000);
+        assert_eq!(rows[99999], Value::Struct(vec![Value::Int(99999 * 7919), Value::String(format!("{:x}", 99999i64 * 104729))]));
+
+        // Stripes flushed in the background are padded too.
+        let schema = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let config = Config::new().with_stripe_size(50000).with_block_padding(block_size);
+        let mut writer = Writer::new(Vec::new(), &schema, config).unwrap().with_background_flush(2);
+        for i in 0..100000 {
+            writer.write_row(&Value::Struct(vec![Value::Int(i * 7919)])).unwrap();
+        }
+        let out = writer.finish().unwrap();
+        let tail = crate::reader::metadata::FileTail::read(&mut Cursor::new(&out), out.len() as u64).unwrap();
+        let stripes = tail.footer.get_stripes();
+        assert!(stripes.len() > 2);
+        for si in stripes {
+            let end = si.get_offset() + si.get_indexLength() + si.get_dataLength() + si.get_footerLength();
+            assert_eq!(si.get_offset() / block_size, (end - 1) / block_size);
+        }
+        assert!(stripes.iter().any(|si| si.get_offset() % block_size == 0));
+    }
+
+    #[test]
+    fn test_atomic_output() {
+        let dir = std::env::temp_dir().join(format!("orc-format-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.orc");
+        let schema = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let num_files = || fs::read_dir(&dir).unwrap().count();
+
+        let mut writer = Writer::create(&path, &schema, Config::new()).unwrap();
+        writer.write_row(&Value::Struct(vec![Value::Int(1)])).unwrap();
+        assert!(!path.exists());
+        assert_eq!(num_files(), 1);
+        writer.finish().unwrap();
+        assert_eq!(Reader::new(File::open(&path).unwrap()).unwrap().num_rows(), 1);
+        assert_eq!(num_files(), 1);
+
+        // Neither an aborted nor a dropped writer leaves anything behind, or replaces the file.
+        let mut writer = Writer::create(&path, &schema, Config::new()).unwrap();
+        writer.write_row(&Value::Struct(vec![Value::Int(2)])).unwrap();
+        writer.abort().unwrap();
+        let writer = Writer::create(&path, &schema, Config::new()).unwrap().with_background_flush(1);
+        drop(writer);
+        assert_eq!(num_files(), 1);
+        assert_eq!(Reader::new(File::open(&path).unwrap()).unwrap().num_rows(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = std::env::temp_dir().join(format!("orc-format-append-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.orc");
+        let schema = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let config = || Config::new().with_compression(ZstdCompression::new().build());
+        let row = |i: i64| Value::Struct(vec![if i % 10 == 0 { Value::Null } else { Value::Int(i) }]);
+
+        let mut writer = Writer::create(&path, &schema, config()).unwrap();
+        for i in 0..50 {
+            writer.write_row(&row(i)).unwrap();
+        }
+        writer.finish().unwrap();
+        let mut writer = Writer::append(&path, &schema, config()).unwrap();
+        for i in 50..100 {
+            writer.write_row(&row(i)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut reader = Reader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.num_stripes(), 2);
+        assert_eq!(reader.rows().unwrap(), (0..100).map(row).collect::<Vec<_>>());
+        let mut file = File::open(&path).unwrap();
+        let len = file.metadata().unwrap().len();
+        let tail = FileTail::read(&mut file, len).unwrap();
+        let stats = &tail.footer.get_statistics()[1];
+        assert_eq!(stats.get_numberOfValues(), 90);
+        assert!(stats.get_hasNull());
+        assert_eq!(stats.get_intStatistics().get_minimum(), 1);
+        assert_eq!(stats.get_intStatistics().get_maximum(), 99);
+        assert_eq!(stats.get_intStatistics().get_sum(), (0..100).filter(|i| i % 10 != 0).sum::<i64>());
+
+        // The file is untouched until the append finishes, and an aborted append leaves it as it was.
+        let contents = fs::read(&path).unwrap();
+        let mut writer = Writer::append(&path, &schema, config()).unwrap();
+        writer.write_row(&row(100)).unwrap();
+        writer.write_intermediate_footer().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), contents);
+        writer.abort().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), contents);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(Writer::append(&path, &schema, Config::new()).err().unwrap().kind(), ErrorKind::InvalidInput);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_stripes() {
+        let config = || Config::new().with_compression(ZstdCompression::new().build()).with_row_index_stride(100);
+        let first = write_rows(config(), 1000).finish().unwrap();
+        let second = write_rows(config().with_stripe_row_limit(300), 1000).finish().unwrap();
+        let mut writer = write_rows(config(), 10);
+        assert_eq!(writer.copy_stripes(Cursor::new(&first)).unwrap(), 1000);
+        assert_eq!(writer.copy_stripes(Cursor::new(&second)).unwrap(), 1000);
+        let mismatched = write_rows(Config::new().with_row_index_stride(100), 10).finish().unwrap();
+        assert_eq!(writer.copy_stripes(Cursor::new(&mismatched)).err().unwrap().kind(), ErrorKind::InvalidInput);
+        let merged = writer.finish().unwrap();
+
+        let rows = |bytes: &Vec<u8>| Reader::new(Cursor::new(bytes)).unwrap().rows().unwrap();
+        let mut expected = rows(&write_rows(Config::new(), 10).finish().unwrap());
+        expected.extend(rows(&first));
+        expected.extend(rows(&second));
+        let mut reader = Reader::new(Cursor::new(&merged)).unwrap();
+        assert_eq!(reader.num_stripes(), 6);
+        assert_eq!(reader.rows().unwrap(), expected);
+        let tail = FileTail::read(&mut Cursor::new(&merged), merged.len() as u64).unwrap();
+        let stats = &tail.footer.get_statistics()[1];
+        assert_eq!(stats.get_numberOfValues(), 2010);
+        assert_eq!(stats.get_intStatistics().get_maximum(), 999 * 7919);
+    }
+
+    #[test]
+    fn test_copy_stripes_without_bounds() {
+        // A file whose stripe statistics leave out the min and max, as some writers do.
+        let full = write_rows(Config::new(), 100).finish().unwrap();
+        let mut input = Cursor::new(&full);
+        let tail = FileTail::read(&mut input, full.len() as u64).unwrap();
+        let metadata = tail.read_metadata(&mut input).unwrap();
+        let info = &tail.footer.get_stripes()[0];
+        let footer = crate::reader::read_stripe_footer(&mut input, tail.decompression(), info).unwrap();
+        let mut statistics = metadata.get_stripeStats()[0].get_colStats().to_vec();
+        statistics[1].mut_intStatistics().clear_minimum();
+        statistics[1].mut_intStatistics().clear_maximum();
+        statistics[2].mut_stringStatistics().clear_maximum();
+        let index_end = info.get_offset() + info.get_indexLength();
+        let mut writer = write_rows(Config::new(), 0);
+        writer.write_encoded_stripe(&mut input, &[info.get_offset()..index_end], 
+            &[index_end..index_end + info.get_dataLength()], &footer, 100, &statistics).unwrap();
+        let stripped = writer.finish().unwrap();
+
+        // The copied stripes' bounds are unknown, so the file has none either.
+        let mut writer = write_rows(Config::new(), 10);
+        writer.copy_stripes(Cursor::new(&stripped)).unwrap();
+        let merged = writer.finish().unwrap();
+        let tail = FileTail::read(&mut Cursor::new(&merged), merged.len() as u64).unwrap();
+        let stats = tail.footer.get_statistics();
+        assert_eq!(stats[1].get_numberOfValues(), 110);
+        assert!(!stats[1].get_intStatistics().has_minimum());
+        assert!(!stats[1].get_intStatistics().has_maximum());
+        assert!(!stats[2].get_stringStatistics().has_minimum());
+        assert!(!stats[2].get_stringStatistics().has_maximum());
+        assert_eq!(stats[1].get_intStatistics().get_sum(), (0..100).sum::<i64>() * 7919 + (0..10).sum::<i64>() * 7919);
+    }
+
+    #[test]
+    fn test_intermediate_footer() {
+        let schema = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let row = |i: i64| Value::Struct(vec![Value::Int(i)]);
+        for &background in &[false, true] {
+            let config = Config::new().with_stripe_size(1000);
+            let mut writer = Writer::new(std::io::Cursor::new(Vec::new()), &schema, config).unwrap();
+            if background {
+                writer = writer.with_background_flush(2);
+            }
+            let mut lengths = Vec::new();
+            for i in 0..3000 {
+                writer.write_row(&row(i)).unwrap();
+                if i % 1000 == 999 {
+                    lengths.push(writer.write_intermediate_footer().unwrap());
+                    assert_eq!(writer.bytes_written(), *lengths.last().unwrap());
+                }
+            }
+            for i in 3000..3500 {
+                writer.write_row(&row(i)).unwrap();
+            }
+            let out = writer.finish().unwrap().into_inner();
+
+            // Each intermediate file holds the rows written before its tail, and its content ends 
+            // where the tail starts.
+            for (n, &len) in lengths.iter().enumerate() {
+                let mut reader = Reader::new(std::io::Cursor::new(&out[..len as usize])).unwrap();
+                let rows = reader.rows().unwrap();
+                assert_eq!(rows, (0..1000 * (n as i64 + 1)).map(row).collect::<Vec<_>>());
+                let tail = FileTail::read(&mut std::io::Cursor::new(&out[..len as usize]), len).unwrap();
+                assert_eq!(tail.footer.get_contentLength(), tail.metadata_start - Writer::<Vec<u8>>::HEADER_LENGTH);
+            }
+            let mut reader = Reader::new(std::io::Cursor::new(out)).unwrap();
+            assert_eq!(reader.rows().unwrap(), (0..3500).map(row).collect::<Vec<_>>());
+        }
+    }
+}
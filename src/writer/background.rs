@@ -0,0 +1,138 @@
+use std::io::{Error, Result, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use crate::memory::MemoryAccount;
+use super::count_write::CountWrite;
+use super::stripe::{Stripe, StripeInfo};
+
+enum Job {
+    Flush(Box<Stripe>, bool),
+    // Hand the output back to the writer until it is returned
+    Lend,
+}
+
+/// A thread which finishes stripes and writes them to the output, so that the writer can go on
+/// filling a fresh stripe in the meantime. Finished stripes are handed back for reuse.
+pub struct BackgroundFlush<W: Write> {
+    sender: SyncSender<Job>,
+    done: Receiver<(Stripe, Vec<StripeInfo>)>,
+    lent: Receiver<CountWrite<W>>,
+    returned: Sender<CountWrite<W>>,
+    handle: JoinHandle<Result<CountWrite<W>>>,
+    spare: Option<Stripe>,
+    // The length of the output, updated after each stripe
+    written: Arc<AtomicU64>,
+}
+
+impl<W: Write + Send + 'static> BackgroundFlush<W> {
+    /// Takes over the output. At most `max_pending` stripes are in flight at once (queued or being
+    /// written); `submit` blocks beyond that.
+    pub fn start(mut out: CountWrite<W>, memory: Arc<MemoryAccount>, max_pending: usize) -> Self {
+        assert!(max_pending > 0);
+        let (sender, receiver) = sync_channel::<Job>(max_pending - 1);
+        let (done_sender, done) = channel();
+        let (lent_sender, lent) = channel();
+        let (returned, returned_receiver) = channel();
+        let written = Arc::new(AtomicU64::new(out.pos() as u64));
+        let thread_written = written.clone();
+        let handle = thread::spawn(move || {
+            for job in receiver {
+                match job {
+                    Job::Flush(mut stripe, release_memory) => {
+                        let mut stripe_infos: Vec<StripeInfo> = Vec::new();
+                        stripe.offset = out.pos() as u64;
+                        memory.set_releasing(release_memory);
+                        let result = stripe.finish(&mut out, &mut stripe_infos);
+                        memory.set_releasing(false);
+                        result?;
+                        thread_written.store(out.pos() as u64, Ordering::Relaxed);
+                        if done_sender.send((*stripe, stripe_infos)).is_err() {
+                            break;
+                        }
+                    }
+                    Job::Lend => {
+                        if lent_sender.send(out).is_err() {
+                            return Err(Error::other("Output lent to a writer which has gone away"));
+                        }
+                        out = match returned_receiver.recv() {
+                            Ok(out) => out,
+                            Err(_) => return Err(Error::other("Lent output was not returned")),
+                        };
+                        thread_written.store(out.pos() as u64, Ordering::Relaxed);
+                    }
+                }
+            }
+            Ok(out)
+        });
+        BackgroundFlush {
+            sender,
+            done,
+            lent,
+            returned,
+            handle,
+            spare: None,
+            written,
+        }
+    }
+}
+
+impl<W: Write> BackgroundFlush<W> {
+    /// Collects the information of the stripes written so far, keeping one of them for reuse.
+    pub fn collect(&mut self, stripe_infos: &mut Vec<StripeInfo>) {
+        while let Ok((stripe, infos)) = self.done.try_recv() {
+            stripe_infos.extend(infos);
+            if self.spare.is_none() {
+                self.spare = Some(stripe);
+            }
+        }
+    }
+
+    /// The number of bytes written to the output so far, not counting queued stripes.
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    /// Returns a finished (and reset) stripe for reuse, if there is one.
+    pub fn take_spare(&mut self) -> Option<Stripe> {
+        self.spare.take()
+    }
+
+    /// Queues a stripe to be written. Returns false if the thread has stopped because of an error,
+    /// which is then returned by `finish`.
+    pub fn submit(&mut self, stripe: Stripe, release_memory: bool) -> bool {
+        self.sender.send(Job::Flush(Box::new(stripe), release_memory)).is_ok()
+    }
+
+    /// Waits for all queued stripes to be written, collecting their information, and takes the
+    /// output until `return_output` is called. Returns `None` if the thread has stopped because of
+    /// an error, which is then returned by `finish`.
+    pub fn lend_output(&mut self, stripe_infos: &mut Vec<StripeInfo>) -> Option<CountWrite<W>> {
+        if self.sender.send(Job::Lend).is_err() {
+            return None;
+        }
+        let out = self.lent.recv().ok()?;
+        self.collect(stripe_infos);
+        Some(out)
+    }
+
+    pub fn return_output(&mut self, out: CountWrite<W>) {
+        // If the thread has gone, the error is returned by `finish`.
+        let _ = self.returned.send(out);
+    }
+
+    /// Waits for all queued stripes to be written, and returns the output.
+    pub fn finish(self, stripe_infos: &mut Vec<StripeInfo>) -> Result<CountWrite<W>> {
+        drop(self.sender);
+        let result = match self.handle.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+        for (_, infos) in self.done.try_iter() {
+            stripe_infos.extend(infos);
+        }
+        result
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::{Result, Error, ErrorKind};
+use std::marker::PhantomData;
+
+use super::data::{
+    Data, BaseData, BooleanData, LongData, FloatData, DoubleData, TimestampData, DecimalData, StringData,
+    BinaryData, StructData, ListData, MapData, UnionData,
+};
+
+/// A column type which a `Column` handle can refer to: one of the `*Data` types, or `Data` itself
+/// to accept a column of any type.
+pub trait ColumnData {
+    /// Returns `Some` if `data` is a column of this type.
+    fn from_data(data: &mut Data) -> Option<&mut Self>;
+
+    #[doc(hidden)]
+    fn matches(data: &Data) -> bool;
+}
+
+macro_rules! impl_column_data {
+    ($t:ty, $variant:ident) => {
+        impl ColumnData for $t {
+            fn from_data(data: &mut Data) -> Option<&mut Self> {
+                match data {
+                    Data::$variant(x) => Some(x),
+                    _ => None,
+                }
+            }
+
+            fn matches(data: &Data) -> bool {
+                matches!(data, Data::$variant(_))
+            }
+        }
+    };
+}
+
+impl_column_data!(BooleanData, Boolean);
+impl_column_data!(LongData, Long);
+impl_column_data!(FloatData, Float);
+impl_column_data!(DoubleData, Double);
+impl_column_data!(TimestampData, Timestamp);
+impl_column_data!(DecimalData, Decimal);
+impl_column_data!(StringData, String);
+impl_column_data!(BinaryData, Binary);
+impl_column_data!(StructData, Struct);
+impl_column_data!(ListData, List);
+impl_column_data!(MapData, Map);
+impl_column_data!(UnionData, Union);
+
+impl ColumnData for Data {
+    fn from_data(data: &mut Data) -> Option<&mut Self> {
+        Some(data)
+    }
+
+    fn matches(_data: &Data) -> bool {
+        true
+    }
+}
+
+/// A typed handle to a column of a `Writer`, obtained from `Writer::column`. Since it records the
+/// column's position in the schema rather than a reference, it stays valid across stripes for the
+/// whole life of the writer (and of any other writer with the same schema).
+pub struct Column<T: ColumnData> {
+    path: Vec<usize>,
+    column_id: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: ColumnData> Clone for Column<T> {
+    fn clone(&self) -> Self {
+        Column {
+            path: self.path.clone(),
+            column_id: self.column_id,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ColumnData> Column<T> {
+    /// Resolves a dot-separated column name relative to `root`; the empty name refers to `root` itself.
+    pub(crate) fn resolve(root: &Data, name: &str) -> Result<Self> {
+        let mut path: Vec<usize> = Vec::new();
+        let mut data = root;
+        if !name.is_empty() {
+            for part in name.split('.') {
+                let (i, child) = find_child(data, part)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("No column named {}", name)))?;
+                path.push(i);
+                data = child;
+            }
+        }
+        if !T::matches(data) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "Column {} has type {}, which does not match the requested handle type",
+                name, data.type_name())));
+        }
+        Ok(Column {
+            path,
+            column_id: data.column_id(),
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn column_id(&self) -> u32 {
+        self.column_id
+    }
+
+    /// Returns the column in the data tree `root`, walking down from it along the handle's path, so
+    /// each call costs one step per level of nesting. Returns an error if `root` does not have the
+    /// schema that the handle was resolved against.
+    pub(crate) fn get<'a>(&self, root: &'a mut Data) -> Result<&'a mut T> {
+        let mismatch = || Error::new(ErrorKind::InvalidInput, "Column handle does not match the writer's schema");
+        let mut data = root;
+        for &i in &self.path {
+            data = child_mut(data, i).ok_or_else(mismatch)?;
+        }
+        T::from_data(data).ok_or_else(mismatch)
+    }
+}
+
+fn find_child<'a>(data: &'a Data, name: &str) -> Option<(usize, &'a Data)> {
+    match data {
+        Data::Struct(d) => {
+            let i = d.field_names().iter().position(|f| f == name)?;
+            Some((i, &d.children[i]))
+        }
+        Data::List(d) if name == "_elem" => Some((0, &d.child)),
+        Data::Map(d) if name == "_key" => Some((0, &d.keys)),
+        Data::Map(d) if name == "_value" => Some((1, &d.values)),
+        Data::Union(d) => {
+            let i: usize = name.parse().ok()?;
+            Some((i, d.children.get(i)?))
+        }
+        _ => None,
+    }
+}
+
+fn child_mut(data: &mut Data, i: usize) -> Option<&mut Data> {
+    match data {
+        Data::Struct(d) => d.children.get_mut(i),
+        Data::List(d) if i == 0 => Some(&mut d.child),
+        Data::Map(d) => match i {
+            0 => Some(&mut d.keys),
+            1 => Some(&mut d.values),
+            _ => None,
+        },
+        Data::Union(d) => d.children.get_mut(i),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Field, Schema};
+    use crate::writer::{Config, Writer};
+
+    #[test]
+    fn test_column_handles() {
+        let schema = Schema::Struct(vec![
+            Field("user".to_owned(), Schema::Struct(vec![
+                Field("id".to_owned(), Schema::Long),
+                Field("tags".to_owned(), Schema::List(Box::new(Schema::String))),
+            ])),
+        ]);
+        let config = Config::new().with_stripe_size(1000);
+        let mut writer = Writer::new(Vec::new(), &schema, config).unwrap();
+        let root = writer.column::<StructData>("").unwrap();
+        let user = writer.column::<StructData>("user").unwrap();
+        let id = writer.column::<LongData>("user.id").unwrap();
+        let tags = writer.column::<ListData>("user.tags").unwrap();
+        let tag = writer.column::<StringData>("user.tags._elem").unwrap();
+        assert_eq!(id.column_id(), 2);
+        assert_eq!(tag.column_id(), 4);
+        assert_eq!(writer.column::<StringData>("user.id").err().unwrap().kind(), ErrorKind::InvalidInput);
+        assert_eq!(writer.column::<LongData>("user.name").err().unwrap().kind(), ErrorKind::InvalidInput);
+        assert!(writer.column::<Data>("user.id").is_ok());
+        let other = Writer::new(Vec::new(), &Schema::Long, Config::new()).unwrap();
+        assert!(writer.get(&other.column::<LongData>("").unwrap()).is_err());
+
+        // Handles remain usable after stripes have been flushed.
+        for i in 0..1000 {
+            writer.get(&root).unwrap().write();
+            writer.get(&user).unwrap().write();
+            writer.get(&id).unwrap().write(i);
+            writer.get(&tags).unwrap().write(1);
+            writer.get(&tag).unwrap().write("x");
+            writer.write_batch(1).unwrap();
+        }
+        assert!(!writer.stripe_infos.is_empty());
+        writer.finish().unwrap();
+    }
+}
@@ -1,34 +1,60 @@
 use crate::protos::orc_proto;
-use std::io::{Result, Write};
+use std::io::{Error, Result, Write};
 use byteorder::{LittleEndian, WriteBytesExt};
 
 
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
 use crate::buffer::Buffer;
+use crate::memory::MemoryAccount;
 use common::{CompressionTrait, Compressor};
+use pool::{CompressedBlock, PendingBlock};
 
 pub use no_compression::NoCompression;
 pub use snappy::SnappyCompression;
 pub use self::zstd::ZstdCompression;
+pub use pool::CompressionPool;
 
 mod common;
 mod no_compression;
 mod snappy;
 mod zstd;
+mod pool;
 
 #[derive(Clone)]
-pub struct Compression(CompressionEnum);
+pub struct Compression {
+    inner: CompressionEnum,
+    // The account of the writer whose streams use this, if any
+    memory: Option<Arc<MemoryAccount>>,
+    // Threads on which to compress blocks, if not on the writer's own thread
+    pool: Option<Arc<CompressionPool>>,
+}
 
 impl Compression {
     pub(crate) fn kind(&self) -> orc_proto::CompressionKind {
-        self.0.kind()
+        self.inner.kind()
     }
 
     pub(crate) fn block_size(&self) -> usize {
-        self.0.block_size()
+        self.inner.block_size()
     }
 
     fn compressor(&self) -> Option<Box<dyn Compressor>> {
-        self.0.compressor()
+        self.inner.compressor()
+    }
+
+    /// Returns a copy whose streams draw their buffers from the given account.
+    pub(crate) fn with_memory(mut self, memory: &Arc<MemoryAccount>) -> Compression {
+        self.memory = Some(memory.clone());
+        self
+    }
+
+    /// Returns a copy whose streams compress their blocks on the given pool.
+    pub(crate) fn with_pool(mut self, pool: &Arc<CompressionPool>) -> Compression {
+        self.pool = Some(pool.clone());
+        self
     }
 }
 
@@ -70,19 +96,19 @@ impl CompressionTrait for CompressionEnum {
 
 impl NoCompression {
     pub fn build(self) -> Compression {
-        Compression(CompressionEnum::No(self))
+        Compression { inner: CompressionEnum::No(self), memory: None, pool: None }
     }
 }
 
 impl SnappyCompression {
     pub fn build(self) -> Compression {
-        Compression(CompressionEnum::Snappy(self))
+        Compression { inner: CompressionEnum::Snappy(self), memory: None, pool: None }
     }
 }
 
 impl ZstdCompression {
     pub fn build(self) -> Compression {
-        Compression(CompressionEnum::Zstd(self))
+        Compression { inner: CompressionEnum::Zstd(self), memory: None, pool: None }
     }
 
 }
@@ -94,23 +120,38 @@ struct BlockInfo {
 
 pub(crate) struct CompressionStream {
     compressor: Option<Box<dyn Compressor>>,
+    block_size: usize,
     buf: Buffer,
     output: Buffer,
     output_block_info: Vec<BlockInfo>,
+    // Uncompressed length of the blocks in `output`
+    input_len: usize,
+    // When compressing on a pool: the settings for the pool's compressors, the blocks still being
+    // compressed (in order), and spare block buffers for reuse
+    background: Option<(Arc<CompressionPool>, CompressionEnum)>,
+    pending: VecDeque<Arc<PendingBlock>>,
+    spare: Vec<Buffer>,
+    // The first failure of a block compressed on the pool, returned when the stream is finished
+    error: Option<Error>,
+    memory: Option<Arc<MemoryAccount>>,
 }
 
+/// A position in a stream, recorded as the index of the compression block rather than its offset,
+/// since the offset is only known once the blocks before it are compressed.
 #[derive(Copy, Clone)]
 pub(crate) struct CompressionStreamPosition {
-    // Offset of the start of the compression block (if applicable)
-    block_start: Option<u64>,
+    // Index of the compression block (if applicable)
+    block: Option<usize>,
     // Offset within the block
     offset: u64,
 }
 
 impl CompressionStreamPosition {
-    pub fn record(&self, out: &mut Vec<u64>) {
-        if let Some(x) = self.block_start {
-            out.push(x);
+    /// Records the position, resolving the block's offset in `stream`, which must be the stream
+    /// the position was taken from and not yet be finished.
+    pub fn record(&self, stream: &CompressionStream, out: &mut Vec<u64>) {
+        if let Some(block) = self.block {
+            out.push(stream.block_start(block));
         }
         out.push(self.offset);
     }
@@ -118,29 +159,82 @@ impl CompressionStreamPosition {
 
 impl CompressionStream {
     pub fn new(compression: &Compression) -> Self {
+        let compressor = compression.compressor();
+        // Without compression, there are no blocks: everything is kept in `buf`.
+        let block_size = if compressor.is_some() { compression.block_size() } else { usize::MAX };
         CompressionStream {
-            compressor: compression.compressor(),
-            buf: Buffer::with_capacity(compression.block_size()),
-            output: Buffer::new(),
+            compressor,
+            block_size,
+            buf: Buffer::with_capacity(compression.block_size(), compression.memory.as_ref()),
+            output: Buffer::new(compression.memory.as_ref()),
             output_block_info: Vec::new(),
+            input_len: 0,
+            background: match &compression.pool {
+                Some(pool) if compression.kind() != orc_proto::CompressionKind::NONE => 
+                    Some((pool.clone(), compression.inner.clone())),
+                _ => None,
+            },
+            pending: VecDeque::new(),
+            spare: Vec::new(),
+            error: None,
+            memory: compression.memory.clone(),
         }
     }
 
     pub fn position(&self) -> CompressionStreamPosition {
         CompressionStreamPosition {
-            block_start: if let Some(_) = &self.compressor {
-                    Some((self.output.len() + 3*self.output_block_info.len()) as u64)
+            block: if let Some(_) = &self.compressor {
+                    Some(self.output_block_info.len() + self.pending.len())
                 } else { None },
             offset: self.buf.len() as u64,
         }
     }
 
+    /// Returns the offset at which the block with the given index starts, waiting for the blocks
+    /// before it to be compressed.
+    fn block_start(&self, index: usize) -> u64 {
+        let num_blocks = self.output_block_info.len() + self.pending.len();
+        assert!(index <= num_blocks, "Position recorded after its stream was reset");
+        let collected = index.min(self.output_block_info.len());
+        let collected_len: usize = self.output_block_info[..collected].iter().map(|info| 3 + info.length).sum();
+        let pending_len: usize = self.pending.iter().take(index - collected).map(|p| 3 + p.wait_len()).sum();
+        (collected_len + pending_len) as u64
+    }
+
     fn finish_block(&mut self) {
         if self.buf.len() == 0 {
             return;
         }
+        if let Some((pool, inner)) = &self.background {
+            let next_buf = match self.spare.pop() {
+                Some(b) => b,
+                None => Buffer::with_capacity(self.block_size, self.memory.as_ref()),
+            };
+            let input = std::mem::replace(&mut self.buf, next_buf);
+            let pending = Arc::new(PendingBlock::new());
+            let result = pending.clone();
+            let inner = inner.clone();
+            let memory = self.memory.clone();
+            pool.execute(move || {
+                let block = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let mut data = Buffer::new(memory.as_ref());
+                    inner.compressor().unwrap().compress(&input, &mut data);
+                    let is_original = data.len() > input.len();
+                    if is_original {
+                        data.resize(0);
+                        data.write_bytes(&input);
+                    }
+                    CompressedBlock { is_original, data, input }
+                }));
+                result.complete(block.map_err(|_| Error::other("Compressing a block panicked")));
+            });
+            self.pending.push_back(pending);
+            self.collect_blocks(false);
+            return;
+        }
         if let Some(compressor) = &mut self.compressor {
             let i = self.output.len();
+            self.input_len += self.buf.len();
             compressor.compress(&self.buf, &mut self.output);
             let len = self.output.len() - i;
             if len > self.buf.len() {
@@ -165,9 +259,39 @@ impl CompressionStream {
         }
     }
 
+    /// Appends the blocks compressed in the background to the output, in order. If `wait` is false,
+    /// stops at the first block that is not yet done.
+    fn collect_blocks(&mut self, wait: bool) {
+        while let Some(pending) = self.pending.front() {
+            if !wait && !pending.is_done() {
+                break;
+            }
+            let block = match pending.take() {
+                Ok(block) => block,
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                    self.pending.pop_front();
+                    continue;
+                }
+            };
+            self.pending.pop_front();
+            self.input_len += block.input.len();
+            self.output.write_bytes(&block.data);
+            self.output_block_info.push(BlockInfo {
+                is_original: block.is_original,
+                length: block.data.len(),
+            });
+            if self.spare.len() < 2 {
+                let mut input = block.input;
+                input.resize(0);
+                self.spare.push(input);
+            }
+        }
+    }
+
     #[inline(always)]
     pub fn write_u8(&mut self, b: u8) {
-        if self.buf.len() >= self.buf.capacity() {
+        if self.buf.len() >= self.block_size {
             self.finish_block();
             self.buf.write_u8(b);
         } else {
@@ -178,8 +302,8 @@ impl CompressionStream {
     #[inline(always)]
     pub fn write_bytes(&mut self, bytes: &[u8]) {
         if let Some(_) = &mut self.compressor {
-            if self.buf.len() + bytes.len() > self.buf.capacity() {
-                let i = self.buf.capacity() - self.buf.len();
+            if self.buf.len() + bytes.len() > self.block_size {
+                let i = self.block_size - self.buf.len();
                 self.buf.write_bytes(&bytes[..i]);
                 self.finish_block();
                 self.buf.resize(0);
@@ -192,9 +316,15 @@ impl CompressionStream {
         }
     }
 
+    /// Writes out the finished stream. The contents are kept until `reset`, so that finishing the
+    /// stream again writes the same bytes, e.g. once to measure the stream and once for real.
     pub fn finish<W: Write>(&mut self, out: &mut W) -> Result<()> {
         if let Some(_) = &self.compressor {
             self.finish_block();
+            self.collect_blocks(true);
+            if let Some(e) = self.error.take() {
+                return Err(e);
+            }
             let mut i = 0;
             for info in &self.output_block_info {
                 let header = info.length * 2 + (info.is_original as usize);
@@ -202,18 +332,37 @@ impl CompressionStream {
                 out.write_all(&self.output[i..(i + info.length)])?;
                 i += info.length;
             }
-            self.output.resize(0);
-            self.output_block_info.clear();
             Ok(())
         } else {
             out.write_all(&self.buf)?;
-            self.buf.resize(0);
             Ok(())
         }
     }
 
+    /// Estimates the size of the stream once finished. Data not yet compressed is assumed to 
+    /// compress as well as the blocks so far.
     pub fn estimated_size(&self) -> usize {
-        self.output.len() + self.buf.len()
+        let uncompressed = self.buf.len() + self.pending.len() * self.block_size;
+        self.output.len() + 3 * self.output_block_info.len() + self.estimate_compressed(uncompressed)
+    }
+
+    /// Estimates the size that `len` more bytes of input will add to the stream.
+    pub fn estimate_compressed(&self, len: usize) -> usize {
+        if self.input_len == 0 {
+            return len;
+        }
+        let compressed_len = self.output.len() + 3 * self.output_block_info.len();
+        (len as f64 * compressed_len as f64 / self.input_len as f64) as usize
+    }
+
+    /// Discards any buffered data, keeping the allocated capacity for reuse.
+    pub fn reset(&mut self) {
+        self.collect_blocks(true);
+        self.error = None;
+        self.buf.clear();
+        self.output.clear();
+        self.output_block_info.clear();
+        self.input_len = 0;
     }
 }
 
@@ -227,3 +376,93 @@ impl Write for CompressionStream {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    fn write_with_positions(compression: &Compression) -> (Vec<u64>, Vec<u8>) {
+        let mut stream = CompressionStream::new(compression);
+        let mut positions = Vec::new();
+        for i in 0..100u32 {
+            positions.push(stream.position());
+            stream.write_bytes(format!("{:x}", i.wrapping_mul(2654435761)).as_bytes());
+        }
+        let mut recorded = Vec::new();
+        for position in &positions {
+            position.record(&stream, &mut recorded);
+        }
+        let mut out = Vec::new();
+        stream.finish(&mut out).unwrap();
+        (recorded, out)
+    }
+
+    #[test]
+    fn test_background_positions() {
+        let compression = ZstdCompression::new().with_block_size(64).build();
+        let expected = write_with_positions(&compression);
+
+        // Positions are taken without waiting for any block to be compressed: here, the pool's only
+        // thread is kept busy until all of them have been taken.
+        let pool = Arc::new(CompressionPool::new(1));
+        let (release, blocked) = channel::<()>();
+        pool.execute(move || blocked.recv().unwrap());
+        let mut stream = CompressionStream::new(&compression.clone().with_pool(&pool));
+        let mut positions = Vec::new();
+        for i in 0..100u32 {
+            positions.push(stream.position());
+            stream.write_bytes(format!("{:x}", i.wrapping_mul(2654435761)).as_bytes());
+        }
+        assert!(stream.pending.len() > 1);
+        release.send(()).unwrap();
+        let mut recorded = Vec::new();
+        for position in &positions {
+            position.record(&stream, &mut recorded);
+        }
+        let mut out = Vec::new();
+        stream.finish(&mut out).unwrap();
+        assert!((recorded, out) == expected);
+        assert!(write_with_positions(&compression.with_pool(&pool)) == expected);
+    }
+
+    #[test]
+    fn test_concurrent_jobs() {
+        // Each job waits for the other to start, which only succeeds if they run at the same time.
+        let pool = CompressionPool::new(2);
+        let (done, results) = channel::<bool>();
+        let (a_started, a_wait) = channel::<()>();
+        let (b_started, b_wait) = channel::<()>();
+        for (started, wait) in vec![(a_started, b_wait), (b_started, a_wait)] {
+            let done = done.clone();
+            pool.execute(move || {
+                started.send(()).unwrap();
+                done.send(wait.recv_timeout(Duration::from_secs(10)).is_ok()).unwrap();
+            });
+        }
+        assert!(results.recv().unwrap() && results.recv().unwrap());
+    }
+
+    #[test]
+    fn test_failed_jobs() {
+        // A panicking job leaves the worker running, and dropping the pool does not panic.
+        let pool = Arc::new(CompressionPool::new(1));
+        pool.execute(|| panic!("job failed"));
+        let (done, result) = channel::<()>();
+        pool.execute(move || done.send(()).unwrap());
+        result.recv_timeout(Duration::from_secs(10)).unwrap();
+
+        // A block which failed to compress makes the stream fail once finished.
+        let compression = ZstdCompression::new().with_block_size(64).build().with_pool(&pool);
+        let mut stream = CompressionStream::new(&compression);
+        stream.write_bytes(&[1; 100]);
+        let failed = Arc::new(PendingBlock::new());
+        failed.complete(Err(Error::other("Compressing a block panicked")));
+        stream.pending.push_back(failed);
+        assert_eq!(stream.finish(&mut Vec::new()).err().unwrap().kind(), ErrorKind::Other);
+        drop(stream);
+        drop(pool);
+    }
+}
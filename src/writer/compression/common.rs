@@ -9,6 +9,6 @@ pub trait CompressionTrait {
     fn compressor(&self) -> Option<Box<dyn Compressor>>;
 }
 
-pub trait Compressor {
+pub trait Compressor: Send {
     fn compress(&mut self, input: &[u8], output: &mut Buffer);
 }
@@ -0,0 +1,107 @@
+use std::io::Result;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::mpsc::{channel, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::buffer::Buffer;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool of threads on which compression blocks are compressed in the background. It is enabled
+/// with `Config::with_compression_pool`, and can be shared by any number of writers.
+pub struct CompressionPool {
+    sender: Mutex<Option<Sender<Job>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl CompressionPool {
+    pub fn new(num_threads: usize) -> Self {
+        assert!(num_threads > 0);
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..num_threads).map(|_| {
+            let receiver = receiver.clone();
+            thread::spawn(move || loop {
+                let job = match receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                // A panicking job must not take the worker down with it; jobs report their own
+                // failures through their results.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            })
+        }).collect();
+        CompressionPool {
+            sender: Mutex::new(Some(sender)),
+            workers,
+        }
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub(crate) fn execute<F: FnOnce() + Send + 'static>(&self, f: F) {
+        self.sender.lock().unwrap().as_ref().unwrap().send(Box::new(f)).unwrap();
+    }
+}
+
+impl Drop for CompressionPool {
+    fn drop(&mut self) {
+        // Closing the channel makes the workers exit once the queued jobs are done.
+        self.sender.lock().unwrap().take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+pub(crate) struct CompressedBlock {
+    pub is_original: bool,
+    pub data: Buffer,
+    // The uncompressed input, handed back for reuse
+    pub input: Buffer,
+}
+
+/// The eventual result of compressing a block on a `CompressionPool`.
+pub(crate) struct PendingBlock {
+    result: Mutex<Option<Result<CompressedBlock>>>,
+    done: Condvar,
+}
+
+impl PendingBlock {
+    pub fn new() -> Self {
+        PendingBlock {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        }
+    }
+
+    pub fn complete(&self, block: Result<CompressedBlock>) {
+        *self.result.lock().unwrap() = Some(block);
+        self.done.notify_all();
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result.lock().unwrap().is_some()
+    }
+
+    /// Waits for the block to be compressed, returning its length, or 0 if compressing it failed.
+    pub fn wait_len(&self) -> usize {
+        let mut result = self.result.lock().unwrap();
+        while result.is_none() {
+            result = self.done.wait(result).unwrap();
+        }
+        result.as_ref().unwrap().as_ref().map_or(0, |block| block.data.len())
+    }
+
+    /// Waits for the block to be compressed, and takes the result.
+    pub fn take(&self) -> Result<CompressedBlock> {
+        let mut result = self.result.lock().unwrap();
+        while result.is_none() {
+            result = self.done.wait(result).unwrap();
+        }
+        result.take().unwrap()
+    }
+}
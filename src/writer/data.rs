@@ -1,7 +1,8 @@
-use std::io::{Write, Result};
+use std::io::{Write, Result, Error, ErrorKind};
 
 use crate::protos::orc_proto;
 use crate::schema::Schema;
+use crate::value::Value;
 use super::Config;
 use super::stripe::StreamInfo;
 use super::statistics::Statistics;
@@ -13,7 +14,7 @@ pub use long::LongData;
 pub use float::FloatData;
 pub use double::DoubleData;
 pub use timestamp::TimestampData;
-pub use decimal::DecimalData;
+pub use decimal::{DecimalData, RoundingMode};
 pub use string::StringData;
 pub use binary::BinaryData;
 pub use struct_::StructData;
@@ -117,6 +118,108 @@ impl Data {
     pub fn unwrap_union(&mut self) -> &mut UnionData {
         if let Data::Union(x) = self { x } else { panic!("unwrap_union called on incorrect type of data"); }
     }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Data::Boolean(_) => "Boolean",
+            Data::Long(x) => x.type_name(),
+            Data::Float(_) => "Float",
+            Data::Double(_) => "Double",
+            Data::Timestamp(_) => "Timestamp",
+            Data::Decimal(_) => "Decimal",
+            Data::String(_) => "String",
+            Data::Binary(_) => "Binary",
+            Data::Struct(_) => "Struct",
+            Data::List(_) => "List",
+            Data::Map(_) => "Map",
+            Data::Union(_) => "Union",
+        }
+    }
+
+    /// Checks that `value` can be written to this column, without writing anything.
+    pub(crate) fn check_value(&self, value: &Value) -> Result<()> {
+        match (self, value) {
+            (_, Value::Null) => Ok(()),
+            (Data::Boolean(_), Value::Bool(_)) => Ok(()),
+            (Data::Long(x), Value::Int(v)) if !matches!(x.schema(), Schema::Date) => x.check_range(*v),
+            (Data::Long(x), Value::Date(_)) if matches!(x.schema(), Schema::Date) => Ok(()),
+            (Data::Float(_), Value::Double(_)) => Ok(()),
+            (Data::Double(_), Value::Double(_)) => Ok(()),
+            (Data::Timestamp(_), Value::Timestamp(_, nanos)) if *nanos < 1_000_000_000 => Ok(()),
+            (Data::Decimal(x), Value::Decimal(v, scale)) => x.rescale(*v, *scale).map(|_| ()),
+            (Data::String(_), Value::String(_)) => Ok(()),
+            (Data::Binary(_), Value::Binary(_)) => Ok(()),
+            (Data::Struct(x), Value::Struct(fields)) if fields.len() == x.children.len() => {
+                for (child, field) in x.children.iter().zip(fields) {
+                    child.check_value(field)?;
+                }
+                Ok(())
+            }
+            (Data::List(x), Value::List(items)) => {
+                for item in items {
+                    x.child.check_value(item)?;
+                }
+                Ok(())
+            }
+            (Data::Map(x), Value::Map(entries)) => {
+                for (key, value) in entries {
+                    x.keys.check_value(key)?;
+                    x.values.check_value(value)?;
+                }
+                Ok(())
+            }
+            (Data::Union(x), Value::Union(tag, v)) if (*tag as usize) < x.children.len() => 
+                x.children[*tag as usize].check_value(v),
+            _ => Err(Error::new(ErrorKind::InvalidInput, format!(
+                "A value of kind {} cannot be written to column {} (type {})", 
+                value.kind(), self.column_id(), self.type_name()))),
+        }
+    }
+
+    /// Writes `value` to this column. The value must already have been validated with `check_value`.
+    pub(crate) fn write_value(&mut self, value: &Value) -> Result<()> {
+        match (self, value) {
+            (data, Value::Null) => data.write_null(),
+            (Data::Boolean(x), Value::Bool(v)) => x.write(*v),
+            (Data::Long(x), Value::Int(v)) => x.try_write(*v)?,
+            (Data::Long(x), Value::Date(v)) => x.write_date_days(*v),
+            (Data::Float(x), Value::Double(v)) => x.write(*v as f32),
+            (Data::Double(x), Value::Double(v)) => x.write(*v),
+            (Data::Timestamp(x), Value::Timestamp(sec, nanos)) => x.write_nanos_epoch(*sec, *nanos),
+            (Data::Decimal(x), Value::Decimal(v, scale)) => {
+                let v = x.rescale(*v, *scale)?;
+                x.try_write_i128(v)?;
+            }
+            (Data::String(x), Value::String(v)) => x.write(v),
+            (Data::Binary(x), Value::Binary(v)) => x.write(v),
+            (Data::Struct(x), Value::Struct(fields)) => {
+                for (child, field) in x.children.iter_mut().zip(fields) {
+                    child.write_value(field)?;
+                }
+                x.write();
+            }
+            (Data::List(x), Value::List(items)) => {
+                for item in items {
+                    x.child.write_value(item)?;
+                }
+                x.write(items.len() as u64);
+            }
+            (Data::Map(x), Value::Map(entries)) => {
+                for (key, value) in entries {
+                    x.keys.write_value(key)?;
+                    x.values.write_value(value)?;
+                }
+                x.write(entries.len() as u64);
+            }
+            (Data::Union(x), Value::Union(tag, v)) => {
+                x.children[*tag as usize].write_value(v)?;
+                x.write(*tag as usize);
+            }
+            (data, value) => panic!("A value of kind {} cannot be written to column {} (type {})", 
+                value.kind(), data.column_id(), data.type_name()),
+        }
+        Ok(())
+    }
 }
 
 impl GenericData for Data {
@@ -158,6 +261,23 @@ impl BaseData for Data {
         }
     }
 
+    fn reset(&mut self) {
+        match self {
+            Data::Boolean(x) => x.reset(),
+            Data::Long(x) => x.reset(),
+            Data::Float(x) => x.reset(),
+            Data::Double(x) => x.reset(),
+            Data::Timestamp(x) => x.reset(),
+            Data::Decimal(x) => x.reset(),
+            Data::String(x) => x.reset(),
+            Data::Binary(x) => x.reset(),
+            Data::Struct(x) => x.reset(),
+            Data::List(x) => x.reset(),
+            Data::Map(x) => x.reset(),
+            Data::Union(x) => x.reset(),
+        }
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         match self {
             Data::Boolean(x) => x.write_index_streams(out, stream_infos_out),
@@ -7,7 +7,7 @@ use crate::writer::compression::{CompressionStream, CompressionStreamPosition};
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, UnsignedIntRLEv1, IntRLEv1Position};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, BinaryStatistics};
-use crate::writer::data::common::{BaseData, GenericData, write_index};
+use crate::writer::data::common::{BaseData, GenericData, write_index, ESTIMATED_INDEX_ENTRY_SIZE, row_group_chunks, check_offsets, is_valid};
 
 
 pub struct BinaryData {
@@ -39,12 +39,12 @@ struct BinaryRowIndexEntry {
 }
 
 impl BinaryDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &BinaryDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.data.record(out);
-        self.lengths.record(out);
+        self.data.record(&streams.data, out);
+        self.lengths.record(streams.lengths.stream(), out);
     }
 }
 
@@ -56,6 +56,12 @@ impl BinaryDataStreams {
             lengths: self.lengths.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.data.reset();
+        self.lengths.reset();
+    }
 }
 
 impl BinaryData {
@@ -86,6 +92,38 @@ impl BinaryData {
         self.check_row_group();
     }
 
+    /// Writes a batch of values stored contiguously in `data`, where value `i` is 
+    /// `data[offsets[i]..offsets[i + 1]]` (so `offsets` has one more entry than there are rows). 
+    /// Where `validity` is given and `false`, the row is null and its offsets are ignored. Nothing 
+    /// is written if the validity slice or the offsets of a value do not fit the batch.
+    pub fn write_offsets(&mut self, data: &[u8], offsets: &[i32], validity: Option<&[bool]>) -> Result<()> {
+        if offsets.is_empty() {
+            panic!("offsets must contain at least one entry");
+        }
+        let num_rows = offsets.len() - 1;
+        check_offsets(data, offsets, validity, self.column_id, "Binary")?;
+        let chunks = row_group_chunks(num_rows, self.row_group_stats.num_values, self.config.row_index_stride);
+        for range in chunks {
+            let mut chunk_stats = BinaryStatistics::new();
+            for i in range.clone() {
+                if is_valid(validity, i) {
+                    let x = &data[offsets[i] as usize..offsets[i + 1] as usize];
+                    self.streams.present.write(true);
+                    self.streams.data.write_bytes(x);
+                    self.streams.lengths.write(x.len() as u64);
+                    chunk_stats.num_present += 1;
+                    chunk_stats.sum_lengths += x.len() as u64;
+                } else {
+                    self.streams.present.write(false);
+                }
+            }
+            chunk_stats.num_values = range.len() as u64;
+            self.row_group_stats.merge(&chunk_stats);
+            self.check_row_group();
+        }
+        Ok(())
+    }
+
     fn check_row_group(&mut self) {
         if self.row_group_stats.num_values == self.config.row_index_stride as u64 {
             self.finish_row_group();
@@ -116,13 +154,21 @@ impl GenericData for BinaryData {
 impl BaseData for BinaryData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = BinaryStatistics::new();
+        self.row_group_stats = BinaryStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Binary(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -177,6 +223,7 @@ impl BaseData for BinaryData {
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.data.estimated_size() + 
             self.streams.lengths.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
@@ -6,7 +6,7 @@ use crate::writer::count_write::CountWrite;
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, BooleanStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE, row_group_chunks, check_validity, is_valid};
 
 
 pub struct BooleanData {
@@ -36,11 +36,11 @@ struct BooleanRowIndexEntry {
 }
 
 impl BooleanDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &BooleanDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.data.record(out);
+        self.data.record(streams.data.stream(), out);
     }
 }
 
@@ -51,6 +51,11 @@ impl BooleanDataStreams {
             data: self.data.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.data.reset();
+    }
 }
 
 impl BooleanData {
@@ -79,6 +84,33 @@ impl BooleanData {
         self.check_row_group();
     }
 
+    /// Writes a batch of values, one per row. Where `validity` is given and `false`, the row is
+    /// null and the corresponding entry of `values` is ignored.
+    pub fn write_slice(&mut self, values: &[bool], validity: Option<&[bool]>) {
+        check_validity(validity, values.len());
+        let chunks = row_group_chunks(values.len(), self.row_group_stats.num_values, self.config.row_index_stride);
+        for range in chunks {
+            let mut chunk_stats = BooleanStatistics::new();
+            for i in range.clone() {
+                if is_valid(validity, i) {
+                    let x = values[i];
+                    self.streams.present.write(true);
+                    self.streams.data.write(x);
+                    if x {
+                        chunk_stats.num_true += 1;
+                    } else {
+                        chunk_stats.num_false += 1;
+                    }
+                } else {
+                    self.streams.present.write(false);
+                }
+            }
+            chunk_stats.num_values = range.len() as u64;
+            self.row_group_stats.merge(&chunk_stats);
+            self.check_row_group();
+        }
+    }
+
     fn check_row_group(&mut self) {
         if self.row_group_stats.num_values == self.config.row_index_stride as u64 {
             self.finish_row_group();
@@ -109,13 +141,21 @@ impl GenericData for BooleanData {
 impl BaseData for BooleanData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = BooleanStatistics::new();
+        self.row_group_stats = BooleanStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
      fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Boolean(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -159,6 +199,7 @@ impl BaseData for BooleanData {
 
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.data.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
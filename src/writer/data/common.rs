@@ -1,4 +1,5 @@
-use std::io::{Write, Result};
+use std::io::{Error, ErrorKind, Write, Result};
+use std::ops::Range;
 use protobuf::{CodedOutputStream, Message, RepeatedField};
 
 use crate::protos::orc_proto;
@@ -13,6 +14,9 @@ pub trait GenericData {
 
 pub trait BaseData: GenericData {
     fn column_id(&self) -> u32;
+    /// Clears all written values and statistics, as at the start of a new stripe, while keeping the 
+    /// allocated buffers for reuse.
+    fn reset(&mut self);
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()>;
     fn write_data_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()>;
     fn column_encodings(&self, out: &mut Vec<orc_proto::ColumnEncoding>);
@@ -21,6 +25,63 @@ pub trait BaseData: GenericData {
     fn estimated_size(&self) -> usize;
 }
 
+/// Rough encoded size of a row index entry (positions and statistics), for estimating stripe sizes.
+pub const ESTIMATED_INDEX_ENTRY_SIZE: usize = 32;
+
+/// Splits a batch of `num_rows` rows into ranges that end at row group boundaries, given the number
+/// of rows already written to the current row group. A stride of 0 means there are no row group
+/// boundaries, so the whole batch is one range.
+pub fn row_group_chunks(num_rows: usize, rows_in_group: u64, row_index_stride: u32) -> impl Iterator<Item = Range<usize>> {
+    let stride = if row_index_stride == 0 { usize::MAX } else { row_index_stride as usize };
+    let mut start = 0;
+    let mut end = stride - rows_in_group as usize;
+    std::iter::from_fn(move || {
+        if start >= num_rows {
+            return None;
+        }
+        let range = start..end.min(num_rows);
+        start = end;
+        end = end.saturating_add(stride);
+        Some(range)
+    })
+}
+
+pub fn check_validity(validity: Option<&[bool]>, num_rows: usize) {
+    if let Some(v) = validity {
+        if v.len() != num_rows {
+            panic!("The length of the validity slice ({}) does not match the number of rows ({})", v.len(), num_rows);
+        }
+    }
+}
+
+/// Checks a batch given as `data` and `offsets`, as taken by `write_offsets`: the validity slice must
+/// have an entry per row, and the offsets of each non-null value must be in order within `data`.
+pub fn check_offsets(data: &[u8], offsets: &[i32], validity: Option<&[bool]>, column_id: u32, type_name: &str) -> Result<()> {
+    let num_rows = offsets.len() - 1;
+    if let Some(v) = validity {
+        if v.len() != num_rows {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "The length of the validity slice ({}) does not match the number of rows ({})", v.len(), num_rows)));
+        }
+    }
+    for i in 0..num_rows {
+        let (start, end) = (offsets[i], offsets[i + 1]);
+        if is_valid(validity, i) && (start < 0 || start > end || end as usize > data.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "In column {} (type {}), the offsets of value {} of the batch ({}..{}) are not within the data ({} bytes)",
+                column_id, type_name, i, start, end, data.len())));
+        }
+    }
+    Ok(())
+}
+
+pub fn is_valid(validity: Option<&[bool]>, i: usize) -> bool {
+    match validity {
+        Some(v) => v[i],
+        None => true,
+    }
+}
+
 pub fn write_index<W: Write>(
         entries: Vec<orc_proto::RowIndexEntry>, 
         column_id: u32,
@@ -46,3 +107,16 @@ pub fn write_index<W: Write>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_group_chunks() {
+        assert_eq!(row_group_chunks(25, 3, 10).collect::<Vec<_>>(), vec![0..7, 7..17, 17..25]);
+        assert_eq!(row_group_chunks(20, 0, 10).collect::<Vec<_>>(), vec![0..10, 10..20]);
+        assert_eq!(row_group_chunks(0, 5, 10).count(), 0);
+        assert_eq!(row_group_chunks(25, 3, 0).collect::<Vec<_>>(), vec![0..25]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::io::{Write, Result};
+use std::io::{Write, Result, Error, ErrorKind};
 
 use crate::protos::orc_proto;
 use crate::schema::Schema;
@@ -8,9 +8,28 @@ use crate::writer::compression::{CompressionStream, CompressionStreamPosition};
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, SignedIntRLEv1, IntRLEv1Position, VarInt};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, DecimalStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE};
 
 
+/// How to round a decimal value that has more fractional digits than the column's scale.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round towards the nearest neighbor, with ties rounded away from zero.
+    HalfUp,
+    /// Round towards the nearest neighbor, with ties rounded to the even neighbor.
+    HalfEven,
+    /// Round towards zero (truncate).
+    Down,
+    /// Round away from zero.
+    Up,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceiling,
+    /// Reject any value that cannot be represented exactly.
+    Unnecessary,
+}
+
 pub struct DecimalData {
     pub(crate) column_id: u32,
     precision: u32,
@@ -44,12 +63,12 @@ struct DecimalRowIndexEntry {
 }
 
 impl DecimalDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &DecimalDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.data.record(out);
-        self.secondary_scale.record(out);
+        self.data.record(&streams.data, out);
+        self.secondary_scale.record(streams.secondary_scale.stream(), out);
     }
 }
 
@@ -61,13 +80,24 @@ impl DecimalDataStreams {
             secondary_scale: self.secondary_scale.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.data.reset();
+        self.secondary_scale.reset();
+    }
 }
 
 impl DecimalData {
+    /// The largest precision whose unscaled values fit in an `i128`
+    pub const MAX_PRECISION: u32 = 38;
+
     pub(crate) fn new(schema: &Schema, config: &Config, column_id: &mut u32) -> Self {
         let cid = *column_id;
         *column_id += 1;
         if let Schema::Decimal(precision, scale) = schema {
+            assert!(*precision <= Self::MAX_PRECISION, "Decimal precision {} exceeds {}", precision, Self::MAX_PRECISION);
+            assert!(scale <= precision, "Decimal scale {} exceeds the precision {}", scale, precision);
             let streams = DecimalDataStreams {
                 present: BooleanRLE::new(&config.compression),
                 data: CompressionStream::new(&config.compression),
@@ -105,15 +135,128 @@ impl DecimalData {
         }
     }
 
+    /// Writes an unscaled value. If range checks are enabled in the `Config`, this panics on a 
+    /// value with more digits than the column's precision.
     pub fn write_i64(&mut self, x: i64) {
-        self.streams.present.write(true);
-        x.write_varint(&mut self.streams.data);
-        self.streams.secondary_scale.write(self.scale as i64);
-        self.row_group_stats.update(x as i128);
-        self.check_row_group();
+        self.write_i128(x as i128);
     }
 
+    /// Writes an unscaled value. If range checks are enabled in the `Config`, this panics on a 
+    /// value with more digits than the column's precision.
     pub fn write_i128(&mut self, x: i128) {
+        if self.config.range_checks {
+            if let Err(e) = self.check_precision(x) {
+                panic!("{}", e);
+            }
+        }
+        self.write_unchecked(x);
+    }
+
+    /// Writes an unscaled value, returning an error (and writing nothing) if it has more digits
+    /// than the column's precision.
+    pub fn try_write_i64(&mut self, x: i64) -> Result<()> {
+        self.try_write_i128(x as i128)
+    }
+
+    /// Writes an unscaled value, returning an error (and writing nothing) if it has more digits
+    /// than the column's precision.
+    pub fn try_write_i128(&mut self, x: i128) -> Result<()> {
+        self.check_precision(x)?;
+        self.write_unchecked(x);
+        Ok(())
+    }
+
+    /// Writes a value given in decimal notation (e.g. "-123.45" or "1.2345e2"), rescaling it 
+    /// to the column's scale using the configured rounding mode.
+    pub fn write_str(&mut self, s: &str) -> Result<()> {
+        let parsed = parse_decimal(s).ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+            format!("In column {} (type Decimal), invalid decimal value {:?}", self.column_id, s)))?;
+        self.write_digits(parsed.negative, &parsed.digits, parsed.point)
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    pub fn write_rust_decimal(&mut self, x: &rust_decimal::Decimal) -> Result<()> {
+        let digits = x.mantissa().abs().to_string();
+        let point = digits.len() as i64 - x.scale() as i64;
+        self.write_digits(x.is_sign_negative(), digits.as_bytes(), point)
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    pub fn write_big_decimal(&mut self, x: &bigdecimal::BigDecimal) -> Result<()> {
+        let (mantissa, scale) = x.as_bigint_and_exponent();
+        let negative = mantissa.sign() == bigdecimal::num_bigint::Sign::Minus;
+        let digits = mantissa.magnitude().to_string();
+        let point = digits.len() as i64 - scale;
+        self.write_digits(negative, digits.as_bytes(), point)
+    }
+
+    fn write_digits(&mut self, negative: bool, digits: &[u8], point: i64) -> Result<()> {
+        let x = self.scale_digits(negative, digits, point)?;
+        self.write_unchecked(x);
+        Ok(())
+    }
+
+    /// Returns the unscaled value (in the column's scale) of `unscaled` given at scale `scale`,
+    /// rounding if necessary, or an error if it does not fit the column's precision.
+    pub(crate) fn rescale(&self, unscaled: i128, scale: u32) -> Result<i128> {
+        if scale == self.scale {
+            self.check_precision(unscaled)?;
+            return Ok(unscaled);
+        }
+        let digits = unscaled.unsigned_abs().to_string();
+        self.scale_digits(unscaled < 0, digits.as_bytes(), digits.len() as i64 - scale as i64)
+    }
+
+    /// Converts the value whose magnitude is given by ASCII `digits`, with the decimal point
+    /// placed before `digits[point]` (`point` may lie outside the digits), to an unscaled value 
+    /// in the column's scale.
+    fn scale_digits(&self, negative: bool, digits: &[u8], point: i64) -> Result<i128> {
+        // Digits at index `end` and beyond are below the column's scale, and are rounded off.
+        let end = point + self.scale as i64;
+        let digit_at = |i: i64| if i >= 0 && i < digits.len() as i64 { digits[i as usize] - b'0' } else { 0 };
+        let first_dropped = digit_at(end);
+        let rest_nonzero = digits.iter().enumerate()
+            .any(|(i, &d)| i as i64 > end && d != b'0');
+
+        // Leading zeros are skipped, and a value with more digits than any precision allows is 
+        // rejected before looping over them, however large its exponent.
+        let first_nonzero = digits.iter().position(|&d| d != b'0').map_or(end, |i| i as i64);
+        if end - first_nonzero > Self::MAX_PRECISION as i64 {
+            return Err(self.precision_error(negative, digits, point));
+        }
+        let mut magnitude: i128 = 0;
+        for i in first_nonzero.max(0)..end.max(0) {
+            magnitude = magnitude * 10 + digit_at(i) as i128;
+        }
+
+        let any_dropped = first_dropped != 0 || rest_nonzero;
+        let round_up = match self.config.rounding_mode {
+            RoundingMode::HalfUp => first_dropped >= 5,
+            RoundingMode::HalfEven => first_dropped > 5 || 
+                (first_dropped == 5 && (rest_nonzero || magnitude % 2 == 1)),
+            RoundingMode::Down => false,
+            RoundingMode::Up => any_dropped,
+            RoundingMode::Floor => negative && any_dropped,
+            RoundingMode::Ceiling => !negative && any_dropped,
+            RoundingMode::Unnecessary => {
+                if any_dropped {
+                    return Err(Error::new(ErrorKind::InvalidInput, format!(
+                        "In column {} (type Decimal), the value {} cannot be represented with scale {} without rounding",
+                        self.column_id, format_digits(negative, digits, point), self.scale)));
+                }
+                false
+            }
+        };
+        if round_up {
+            magnitude += 1;
+        }
+        if magnitude >= 10i128.pow(self.precision) {
+            return Err(self.precision_error(negative, digits, point));
+        }
+        Ok(if negative { -magnitude } else { magnitude })
+    }
+
+    fn write_unchecked(&mut self, x: i128) {
         self.streams.present.write(true);
         x.write_varint(&mut self.streams.data);
         self.streams.secondary_scale.write(self.scale as i64);
@@ -121,11 +264,79 @@ impl DecimalData {
         self.check_row_group();
     }
 
+    fn check_precision(&self, x: i128) -> Result<()> {
+        let limit = 10i128.pow(self.precision);
+        if x >= limit || x <= -limit {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "In column {} (type Decimal), the unscaled value {} exceeds the precision {}", 
+                self.column_id, x, self.precision)));
+        }
+        Ok(())
+    }
+
+    fn precision_error(&self, negative: bool, digits: &[u8], point: i64) -> Error {
+        Error::new(ErrorKind::InvalidInput, format!(
+            "In column {} (type Decimal), the value {} exceeds the precision {} (with scale {})",
+            self.column_id, format_digits(negative, digits, point), self.precision, self.scale))
+    }
+
     pub fn precision(&self) -> u32 { self.precision }
 
     pub fn scale(&self) -> u32 { self.scale }
 }
 
+struct ParsedDecimal {
+    negative: bool,
+    digits: Vec<u8>,
+    point: i64,
+}
+
+fn parse_decimal(s: &str) -> Option<ParsedDecimal> {
+    let s = s.trim();
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i32>().ok()?),
+        None => (s, 0),
+    };
+    let (int_part, frac_part) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    let mut digits: Vec<u8> = Vec::with_capacity(int_part.len() + frac_part.len());
+    for &c in int_part.as_bytes().iter().chain(frac_part.as_bytes()) {
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        digits.push(c);
+    }
+    Some(ParsedDecimal {
+        negative,
+        digits,
+        point: int_part.len() as i64 + exponent as i64,
+    })
+}
+
+fn format_digits(negative: bool, digits: &[u8], point: i64) -> String {
+    let digits = String::from_utf8_lossy(digits);
+    let sign = if negative { "-" } else { "" };
+    if point < -(DecimalData::MAX_PRECISION as i64) || point > (digits.len() + DecimalData::MAX_PRECISION as usize) as i64 {
+        format!("{}0.{}e{}", sign, digits, point)
+    } else if point <= 0 {
+        format!("{}0.{}{}", sign, "0".repeat(-point as usize), digits)
+    } else if point as usize >= digits.len() {
+        format!("{}{}{}", sign, digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        format!("{}{}.{}", sign, &digits[..point as usize], &digits[point as usize..])
+    }
+}
+
 impl GenericData for DecimalData {
     fn write_null(&mut self) {
         self.streams.present.write(false);
@@ -137,13 +348,21 @@ impl GenericData for DecimalData {
 impl BaseData for DecimalData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = DecimalStatistics::new(self.scale);
+        self.row_group_stats = DecimalStatistics::new(self.scale);
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Decimal(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -198,6 +417,7 @@ impl BaseData for DecimalData {
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.data.estimated_size() 
             + self.streams.secondary_scale.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
@@ -208,3 +428,87 @@ impl BaseData for DecimalData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(precision: u32, scale: u32, rounding_mode: RoundingMode, input: &str) -> Result<Option<i128>> {
+        let config = Config::new().with_rounding_mode(rounding_mode);
+        let mut data = DecimalData::new(&Schema::Decimal(precision, scale), &config, &mut 0);
+        data.write_str(input)?;
+        Ok(data.row_group_stats.min)
+    }
+
+    #[test]
+    fn test_write_str() {
+        let cases = vec![
+            ("123.45", RoundingMode::HalfUp, Some(12345)),
+            ("-123.45", RoundingMode::HalfUp, Some(-12345)),
+            ("1.2345e2", RoundingMode::HalfUp, Some(12345)),
+            ("12", RoundingMode::HalfUp, Some(1200)),
+            (".5", RoundingMode::HalfUp, Some(50)),
+            ("0.125", RoundingMode::HalfUp, Some(13)),
+            ("0.125", RoundingMode::HalfEven, Some(12)),
+            ("0.1250001", RoundingMode::HalfEven, Some(13)),
+            ("0.135", RoundingMode::HalfEven, Some(14)),
+            ("-0.125", RoundingMode::HalfUp, Some(-13)),
+            ("0.129", RoundingMode::Down, Some(12)),
+            ("0.121", RoundingMode::Up, Some(13)),
+            ("-0.121", RoundingMode::Floor, Some(-13)),
+            ("-0.121", RoundingMode::Ceiling, Some(-12)),
+            ("0.12", RoundingMode::Unnecessary, Some(12)),
+            ("999.994", RoundingMode::HalfUp, Some(99999)),
+        ];
+        for (input, rounding_mode, expected) in cases {
+            assert_eq!(written(5, 2, rounding_mode, input).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_write_str_errors() {
+        assert!(written(5, 2, RoundingMode::HalfUp, "1000").is_err());
+        assert!(written(5, 2, RoundingMode::HalfUp, "999.995").is_err());
+        assert!(written(5, 2, RoundingMode::Unnecessary, "0.125").is_err());
+        assert!(written(5, 2, RoundingMode::HalfUp, "1.2.3").is_err());
+        assert!(written(5, 2, RoundingMode::HalfUp, "abc").is_err());
+        assert!(written(5, 2, RoundingMode::HalfUp, "").is_err());
+        assert!(written(38, 0, RoundingMode::HalfUp, "1e999999999").is_err());
+        assert_eq!(written(38, 0, RoundingMode::HalfUp, "0e999999999").unwrap(), Some(0));
+        assert_eq!(written(38, 0, RoundingMode::HalfUp, "1e-999999999").unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_precision_check() {
+        let mut data = DecimalData::new(&Schema::Decimal(3, 1), &Config::new(), &mut 0);
+        assert!(data.try_write_i128(999).is_ok());
+        assert!(data.try_write_i128(-999).is_ok());
+        assert!(data.try_write_i128(1000).is_err());
+        assert!(data.try_write_i64(-999).is_ok());
+        assert!(data.try_write_i64(-1000).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "precision 39 exceeds 38")]
+    fn test_max_precision() {
+        DecimalData::new(&Schema::Decimal(39, 0), &Config::new(), &mut 0);
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_write_rust_decimal() {
+        let mut data = DecimalData::new(&Schema::Decimal(5, 2), &Config::new(), &mut 0);
+        data.write_rust_decimal(&rust_decimal::Decimal::new(-12345, 3)).unwrap();
+        assert_eq!(data.row_group_stats.min, Some(-1235));
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn test_write_big_decimal() {
+        let mut data = DecimalData::new(&Schema::Decimal(5, 2), &Config::new(), &mut 0);
+        data.write_big_decimal(&bigdecimal::BigDecimal::new((-12345).into(), 3)).unwrap();
+        data.write_big_decimal(&bigdecimal::BigDecimal::new(3.into(), -2)).unwrap();
+        assert_eq!(data.row_group_stats.min, Some(-1235));
+        assert_eq!(data.row_group_stats.max, Some(30000));
+    }
+}
@@ -8,7 +8,7 @@ use crate::writer::compression::{CompressionStream, CompressionStreamPosition};
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, DoubleStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE, row_group_chunks, check_validity, is_valid};
 
 
 pub struct FloatData {
@@ -38,11 +38,11 @@ struct FloatRowIndexEntry {
 }
 
 impl FloatDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &FloatDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.data.record(out);
+        self.data.record(&streams.data, out);
     }
 }
 
@@ -53,6 +53,11 @@ impl FloatDataStreams {
             data: self.data.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.data.reset();
+    }
 }
 
 impl FloatData {
@@ -98,6 +103,42 @@ impl FloatData {
         self.row_group_stats.update(x as f64);
         self.check_row_group();
     }
+
+    /// Writes a batch of values, one per row. Where `validity` is given and `false`, the row is
+    /// null and the corresponding entry of `values` is ignored.
+    pub fn write_slice(&mut self, values: &[f32], validity: Option<&[bool]>) {
+        check_validity(validity, values.len());
+        let chunks = row_group_chunks(values.len(), self.row_group_stats.num_values, self.config.row_index_stride);
+        for range in chunks {
+            let mut chunk_stats = DoubleStatistics::new();
+            let mut min = f64::INFINITY;
+            let mut max = f64::NEG_INFINITY;
+            let mut num_non_nan = 0;
+            for i in range.clone() {
+                if is_valid(validity, i) {
+                    let x = values[i];
+                    self.streams.present.write(true);
+                    self.streams.data.write_f32::<LittleEndian>(x).unwrap();
+                    chunk_stats.num_present += 1;
+                    if !x.is_nan() {
+                        min = min.min(x as f64);
+                        max = max.max(x as f64);
+                        chunk_stats.sum += x as f64;
+                        num_non_nan += 1;
+                    }
+                } else {
+                    self.streams.present.write(false);
+                }
+            }
+            chunk_stats.num_values = range.len() as u64;
+            if num_non_nan > 0 {
+                chunk_stats.min = Some(min);
+                chunk_stats.max = Some(max);
+            }
+            self.row_group_stats.merge(&chunk_stats);
+            self.check_row_group();
+        }
+    }
 }
 
 impl GenericData for FloatData {
@@ -111,13 +152,21 @@ impl GenericData for FloatData {
 impl BaseData for FloatData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = DoubleStatistics::new();
+        self.row_group_stats = DoubleStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Double(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -162,6 +211,7 @@ impl BaseData for FloatData {
 
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.data.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
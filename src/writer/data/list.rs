@@ -6,17 +6,17 @@ use crate::writer::Config;
 use crate::writer::count_write::CountWrite;
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, UnsignedIntRLEv1, IntRLEv1Position};
 use crate::writer::stripe::StreamInfo;
-use crate::writer::statistics::{Statistics, BaseStatistics, GenericStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::statistics::{Statistics, BaseStatistics, CollectionStatistics};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE};
 use crate::writer::data::Data;
 
 pub struct ListData {
     column_id: u32,
     pub(crate) child: Box<Data>,
     streams: ListDataStreams,
-    stripe_stats: GenericStatistics,
+    stripe_stats: CollectionStatistics,
     num_child_values: u64,
-    row_group_stats: GenericStatistics,
+    row_group_stats: CollectionStatistics,
     row_group_position: ListDataPosition,
     row_index_entries: Vec<ListRowIndexEntry>,
     config: Config,
@@ -35,15 +35,15 @@ struct ListDataPosition {
 
 struct ListRowIndexEntry {
     position: ListDataPosition,
-    stats: GenericStatistics,
+    stats: CollectionStatistics,
 }
 
 impl ListDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &ListDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.lengths.record(out);
+        self.lengths.record(streams.lengths.stream(), out);
     }
 }
 
@@ -54,6 +54,11 @@ impl ListDataStreams {
             lengths: self.lengths.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.lengths.reset();
+    }
 }
 
 impl ListData {
@@ -68,9 +73,9 @@ impl ListData {
             Self {
                 column_id: cid,
                 child: Box::new(Data::new(&child_schema, config, column_id)),
-                stripe_stats: GenericStatistics::new(),
+                stripe_stats: CollectionStatistics::new(),
                 num_child_values: 0,
-                row_group_stats: GenericStatistics::new(),
+                row_group_stats: CollectionStatistics::new(),
                 row_group_position: streams.position(),
                 row_index_entries: Vec::new(),
                 config: config.clone(),
@@ -97,7 +102,7 @@ impl ListData {
                 stats: self.row_group_stats,
             });
             self.row_group_position = self.streams.position();
-            self.row_group_stats = GenericStatistics::new();
+            self.row_group_stats = CollectionStatistics::new();
         }
     }
 
@@ -105,7 +110,7 @@ impl ListData {
         self.streams.present.write(true);
         self.streams.lengths.write(len);
         self.num_child_values += len;
-        self.row_group_stats.update();
+        self.row_group_stats.update(len);
         self.check_row_group();
     }
 
@@ -123,6 +128,16 @@ impl GenericData for ListData {
 impl BaseData for ListData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = CollectionStatistics::new();
+        self.row_group_stats = CollectionStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+        self.num_child_values = 0;
+        self.child.reset();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
 
@@ -130,9 +145,9 @@ impl BaseData for ListData {
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
-            row_index_entry.set_statistics(Statistics::Generic(entry.stats).to_proto());
+            row_index_entry.set_statistics(Statistics::Collection(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
         }
         write_index(row_index_entries, self.column_id(), &self.config.compression, out, stream_infos_out)?;
@@ -177,7 +192,7 @@ impl BaseData for ListData {
 
     fn statistics(&self, out: &mut Vec<Statistics>) {
         assert_eq!(out.len(), self.column_id as usize);
-        out.push(Statistics::Generic(self.stripe_stats));
+        out.push(Statistics::Collection(self.stripe_stats));
         self.child.statistics(out);
     }
 
@@ -193,5 +208,6 @@ impl BaseData for ListData {
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.lengths.estimated_size() + 
             self.child.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 }
@@ -1,4 +1,4 @@
-use std::io::{Write, Result};
+use std::io::{Write, Result, Error, ErrorKind};
 
 use crate::protos::orc_proto;
 use crate::schema::Schema;
@@ -7,7 +7,7 @@ use crate::writer::count_write::CountWrite;
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, SignedIntRLEv1, IntRLEv1Position};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, LongStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE, row_group_chunks, check_validity, is_valid};
 
 
 pub struct LongData {
@@ -38,11 +38,11 @@ struct LongRowIndexEntry {
 }
 
 impl LongDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &LongDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.data.record(out);
+        self.data.record(streams.data.stream(), out);
     }
 }
 
@@ -53,6 +53,11 @@ impl LongDataStreams {
             data: self.data.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.data.reset();
+    }
 }
 
 impl LongData {
@@ -93,13 +98,119 @@ impl LongData {
         }
     }
 
+    /// Writes a value. If range checks are enabled in the `Config`, this panics on a value that
+    /// does not fit the column's type; otherwise the value is stored as-is.
     pub fn write(&mut self, x: i64) {
+        if self.config.range_checks {
+            if let Err(e) = self.check_range(x) {
+                panic!("{}", e);
+            }
+        }
+        self.write_unchecked(x);
+    }
+
+    /// Writes a value, returning an error (and writing nothing) if it does not fit the column's type.
+    pub fn try_write(&mut self, x: i64) -> Result<()> {
+        self.check_range(x)?;
+        self.write_unchecked(x);
+        Ok(())
+    }
+
+    /// Writes a batch of values, one per row. Where `validity` is given and `false`, the row is
+    /// null and the corresponding entry of `values` is ignored.
+    pub fn write_slice(&mut self, values: &[i64], validity: Option<&[bool]>) {
+        check_validity(validity, values.len());
+        if self.config.range_checks {
+            for (i, &x) in values.iter().enumerate() {
+                if is_valid(validity, i) {
+                    if let Err(e) = self.check_range(x) {
+                        panic!("{}", e);
+                    }
+                }
+            }
+        }
+        let chunks = row_group_chunks(values.len(), self.row_group_stats.num_values, self.config.row_index_stride);
+        for range in chunks {
+            let mut chunk_stats = LongStatistics::new();
+            let mut min = i64::MAX;
+            let mut max = i64::MIN;
+            let mut sum: Option<i64> = Some(0);
+            for i in range.clone() {
+                if is_valid(validity, i) {
+                    let x = values[i];
+                    self.streams.present.write(true);
+                    self.streams.data.write(x);
+                    min = min.min(x);
+                    max = max.max(x);
+                    sum = sum.and_then(|s| s.checked_add(x));
+                    chunk_stats.num_present += 1;
+                } else {
+                    self.streams.present.write(false);
+                }
+            }
+            chunk_stats.num_values = range.len() as u64;
+            if chunk_stats.num_present > 0 {
+                chunk_stats.min = Some(min);
+                chunk_stats.max = Some(max);
+                chunk_stats.sum = sum;
+            }
+            self.row_group_stats.merge(&chunk_stats);
+            self.check_row_group();
+        }
+    }
+
+    pub fn write_i16(&mut self, x: i16) {
+        match self.schema {
+            Schema::Short | Schema::Int | Schema::Long => self.write_unchecked(x as i64),
+            _ => panic!("write_i16 called on column {} of type {}", self.column_id, self.type_name()),
+        }
+    }
+
+    pub fn write_i32(&mut self, x: i32) {
+        match self.schema {
+            Schema::Int | Schema::Long => self.write_unchecked(x as i64),
+            _ => panic!("write_i32 called on column {} of type {}", self.column_id, self.type_name()),
+        }
+    }
+
+    /// Writes a date, given as the number of days since the UNIX epoch.
+    pub fn write_date_days(&mut self, days: i32) {
+        match self.schema {
+            Schema::Date => self.write_unchecked(days as i64),
+            _ => panic!("write_date_days called on column {} of type {}", self.column_id, self.type_name()),
+        }
+    }
+
+    fn write_unchecked(&mut self, x: i64) {
         self.streams.present.write(true);
         self.streams.data.write(x);
         self.row_group_stats.update(x);
         self.check_row_group();
     }
 
+    pub(crate) fn check_range(&self, x: i64) -> Result<()> {
+        let (min, max) = match self.schema {
+            Schema::Short => (i16::MIN as i64, i16::MAX as i64),
+            Schema::Int | Schema::Date => (i32::MIN as i64, i32::MAX as i64),
+            _ => return Ok(()),
+        };
+        if x < min || x > max {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "In column {} (type {}), the value {} is out of range", self.column_id, self.type_name(), x)));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self.schema {
+            Schema::Short => "Short",
+            Schema::Int => "Int",
+            Schema::Long => "Long",
+            Schema::Date => "Date",
+            _ => unreachable!(),
+        }
+    }
+
     pub fn schema(&self) -> &Schema {
         &self.schema
     }
@@ -117,13 +228,21 @@ impl GenericData for LongData {
 impl BaseData for LongData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = LongStatistics::new();
+        self.row_group_stats = LongStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Long(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -168,13 +287,82 @@ impl BaseData for LongData {
 
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.data.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
         let rows_written = self.stripe_stats.num_values() + self.row_group_stats.num_values();
         if rows_written != expected_row_count {
-            panic!("In column {} (type Long), the number of values written ({}) does not match the expected number ({})", 
-                self.column_id, rows_written, expected_row_count);
+            panic!("In column {} (type {}), the number of values written ({}) does not match the expected number ({})", 
+                self.column_id, self.type_name(), rows_written, expected_row_count);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(data: &mut LongData) -> (Vec<u8>, Vec<orc_proto::ColumnStatistics>) {
+        let mut out = CountWrite::new(Vec::new());
+        let mut stream_infos = Vec::new();
+        data.write_index_streams(&mut out, &mut stream_infos).unwrap();
+        data.write_data_streams(&mut out, &mut stream_infos).unwrap();
+        let mut stats = Vec::new();
+        data.statistics(&mut stats);
+        (out.into_inner(), stats.iter().map(|s| s.to_proto()).collect())
+    }
+
+    #[test]
+    fn test_write_slice() {
+        let values: Vec<i64> = (0..40).map(|i| i * i - 100).collect();
+        let validity: Vec<bool> = (0..40).map(|i| i % 3 != 0).collect();
+
+        // A stride of 0 writes no row group boundaries.
+        for stride in [7, 0] {
+            let config = Config::new().with_row_index_stride(stride);
+            let mut expected = LongData::new(&Schema::Long, &config, &mut 0);
+            expected.write(5);
+            for i in 0..40 {
+                if validity[i] { expected.write(values[i]); } else { expected.write_null(); }
+            }
+            let mut actual = LongData::new(&Schema::Long, &config, &mut 0);
+            actual.write(5);
+            actual.write_slice(&values, Some(&validity));
+            assert_eq!(serialize(&mut actual), serialize(&mut expected));
+        }
+    }
+
+    #[test]
+    fn test_range_checks() {
+        let config = Config::new();
+        let mut data = LongData::new(&Schema::Short, &config, &mut 0);
+        assert!(data.try_write(32767).is_ok());
+        assert!(data.try_write(-32768).is_ok());
+        assert!(data.try_write(70000).is_err());
+        assert!(data.try_write(-32769).is_err());
+        data.verify_row_count(2);
+
+        let mut data = LongData::new(&Schema::Int, &config, &mut 0);
+        assert!(data.try_write(70000).is_ok());
+        assert!(data.try_write(1 << 31).is_err());
+
+        let mut data = LongData::new(&Schema::Long, &config, &mut 0);
+        assert!(data.try_write(i64::MIN).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_range_checks_config() {
+        let config = Config::new().with_range_checks(true);
+        let mut data = LongData::new(&Schema::Short, &config, &mut 0);
+        data.write(70000);
+    }
+
+    #[test]
+    #[should_panic(expected = "write_i32 called on column 0 of type Short")]
+    fn test_write_i32_on_short() {
+        let mut data = LongData::new(&Schema::Short, &Config::new(), &mut 0);
+        data.write_i32(1);
+    }
+}
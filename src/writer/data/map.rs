@@ -6,8 +6,8 @@ use crate::writer::Config;
 use crate::writer::count_write::CountWrite;
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, UnsignedIntRLEv1, IntRLEv1Position};
 use crate::writer::stripe::StreamInfo;
-use crate::writer::statistics::{Statistics, BaseStatistics, GenericStatistics};
-use crate::writer::data::common::{BaseData, write_index};
+use crate::writer::statistics::{Statistics, BaseStatistics, CollectionStatistics};
+use crate::writer::data::common::{BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE};
 use crate::writer::data::{GenericData, Data};
 
 pub struct MapData {
@@ -15,9 +15,9 @@ pub struct MapData {
     pub(crate) keys: Box<Data>,
     pub(crate) values: Box<Data>,
     streams: MapDataStreams,
-    stripe_stats: GenericStatistics,
+    stripe_stats: CollectionStatistics,
     num_child_values: u64,
-    row_group_stats: GenericStatistics,
+    row_group_stats: CollectionStatistics,
     row_group_position: MapDataPosition,
     row_index_entries: Vec<MapRowIndexEntry>,
     config: Config,
@@ -36,15 +36,15 @@ struct MapDataPosition {
 
 struct MapRowIndexEntry {
     position: MapDataPosition,
-    stats: GenericStatistics,
+    stats: CollectionStatistics,
 }
 
 impl MapDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &MapDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.lengths.record(out);
+        self.lengths.record(streams.lengths.stream(), out);
     }
 }
 
@@ -55,6 +55,11 @@ impl MapDataStreams {
             lengths: self.lengths.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.lengths.reset();
+    }
 }
 
 
@@ -71,9 +76,9 @@ impl MapData {
                 column_id: cid,
                 keys: Box::new(Data::new(&key_schema, config, column_id)),
                 values: Box::new(Data::new(&value_schema, config, column_id)),
-                stripe_stats: GenericStatistics::new(),
+                stripe_stats: CollectionStatistics::new(),
                 num_child_values: 0,
-                row_group_stats: GenericStatistics::new(),
+                row_group_stats: CollectionStatistics::new(),
                 row_group_position: streams.position(),
                 row_index_entries: Vec::new(),
                 config: config.clone(),
@@ -108,7 +113,7 @@ impl MapData {
                 stats: self.row_group_stats,
             });
             self.row_group_position = self.streams.position();
-            self.row_group_stats = GenericStatistics::new();
+            self.row_group_stats = CollectionStatistics::new();
         }
     }
 
@@ -116,7 +121,7 @@ impl MapData {
         self.streams.present.write(true);
         self.streams.lengths.write(len);
         self.num_child_values += len;
-        self.row_group_stats.update();
+        self.row_group_stats.update(len);
         self.check_row_group();
     }
 
@@ -134,6 +139,17 @@ impl GenericData for MapData {
 impl BaseData for MapData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = CollectionStatistics::new();
+        self.row_group_stats = CollectionStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+        self.num_child_values = 0;
+        self.keys.reset();
+        self.values.reset();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
 
@@ -141,9 +157,9 @@ impl BaseData for MapData {
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
-            row_index_entry.set_statistics(Statistics::Generic(entry.stats).to_proto());
+            row_index_entry.set_statistics(Statistics::Collection(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
         }
         write_index(row_index_entries, self.column_id(), &self.config.compression, out, stream_infos_out)?;
@@ -191,7 +207,7 @@ impl BaseData for MapData {
 
     fn statistics(&self, out: &mut Vec<Statistics>) {
         assert_eq!(out.len(), self.column_id as usize);
-        out.push(Statistics::Generic(self.stripe_stats));
+        out.push(Statistics::Collection(self.stripe_stats));
         self.keys.statistics(out);
         self.values.statistics(out);
     }
@@ -209,5 +225,6 @@ impl BaseData for MapData {
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.lengths.estimated_size() + 
             self.keys.estimated_size() + self.values.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 }
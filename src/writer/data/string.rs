@@ -1,4 +1,5 @@
-use std::io::{Write, Result};
+use std::io::{Write, Result, Error, ErrorKind};
+use std::str;
 
 use crate::protos::orc_proto;
 use crate::schema::Schema;
@@ -8,7 +9,7 @@ use crate::writer::compression::{CompressionStream, CompressionStreamPosition};
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, UnsignedIntRLEv1, IntRLEv1Position};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, StringStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE, row_group_chunks, check_offsets, is_valid};
 
 pub struct StringData {
     pub(crate) column_id: u32,
@@ -40,12 +41,12 @@ struct StringRowIndexEntry {
 }
 
 impl StringDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &StringDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.data.record(out);
-        self.lengths.record(out);
+        self.data.record(&streams.data, out);
+        self.lengths.record(streams.lengths.stream(), out);
     }
 }
 
@@ -57,6 +58,12 @@ impl StringDataStreams {
             lengths: self.lengths.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.data.reset();
+        self.lengths.reset();
+    }
 }
 
 impl StringData {
@@ -88,6 +95,68 @@ impl StringData {
         self.check_row_group();
     }
 
+    /// Writes a batch of UTF-8 values stored contiguously in `data`, where value `i` is 
+    /// `data[offsets[i]..offsets[i + 1]]` (so `offsets` has one more entry than there are rows). 
+    /// Where `validity` is given and `false`, the row is null and its offsets are ignored. Nothing 
+    /// is written if the validity slice or the offsets of a value do not fit the batch, or if any 
+    /// value is not valid UTF-8.
+    pub fn write_offsets(&mut self, data: &[u8], offsets: &[i32], validity: Option<&[bool]>) -> Result<()> {
+        if offsets.is_empty() {
+            panic!("offsets must contain at least one entry");
+        }
+        let num_rows = offsets.len() - 1;
+        check_offsets(data, offsets, validity, self.column_id, "String")?;
+        let mut values: Vec<Option<&str>> = Vec::with_capacity(num_rows);
+        for i in 0..num_rows {
+            if is_valid(validity, i) {
+                match str::from_utf8(&data[offsets[i] as usize..offsets[i + 1] as usize]) {
+                    Ok(x) => values.push(Some(x)),
+                    Err(e) => return Err(Error::new(ErrorKind::InvalidData, format!(
+                        "In column {} (type String), value {} of the batch is not valid UTF-8: {}", self.column_id, i, e))),
+                }
+            } else {
+                values.push(None);
+            }
+        }
+
+        let chunks = row_group_chunks(num_rows, self.row_group_stats.num_values, self.config.row_index_stride);
+        for range in chunks {
+            // Track the chunk's extremes by reference, so that only they need to be copied into
+            // the statistics.
+            let mut min: Option<&str> = None;
+            let mut max: Option<&str> = None;
+            let mut num_present = 0;
+            let mut sum_lengths = 0;
+            for value in &values[range.clone()] {
+                if let Some(x) = *value {
+                    self.streams.present.write(true);
+                    self.streams.data.write_bytes(x.as_bytes());
+                    self.streams.lengths.write(x.len() as u64);
+                    if min.is_none() || Some(x) < min {
+                        min = Some(x);
+                    }
+                    if max.is_none() || Some(x) > max {
+                        max = Some(x);
+                    }
+                    num_present += 1;
+                    sum_lengths += x.len() as u64;
+                } else {
+                    self.streams.present.write(false);
+                }
+            }
+            let mut chunk_stats = StringStatistics::new();
+            for x in min.iter().chain(max.iter()) {
+                chunk_stats.update(x);
+            }
+            chunk_stats.num_values = range.len() as u64;
+            chunk_stats.num_present = num_present;
+            chunk_stats.sum_lengths = sum_lengths;
+            self.row_group_stats.merge(&chunk_stats);
+            self.check_row_group();
+        }
+        Ok(())
+    }
+
     fn check_row_group(&mut self) {
         if self.row_group_stats.num_values == self.config.row_index_stride as u64 {
             self.finish_row_group();
@@ -122,13 +191,21 @@ impl GenericData for StringData {
 impl BaseData for StringData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = StringStatistics::new();
+        self.row_group_stats = StringStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::String(entry.stats.clone()).to_proto());
             row_index_entries.push(row_index_entry);
@@ -183,6 +260,7 @@ impl BaseData for StringData {
     fn estimated_size(&self) -> usize {
         self.streams.present.estimated_size() + self.streams.data.estimated_size() + 
             self.streams.lengths.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
@@ -193,3 +271,49 @@ impl BaseData for StringData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(data: &mut StringData) -> (Vec<u8>, Vec<orc_proto::ColumnStatistics>) {
+        let mut out = CountWrite::new(Vec::new());
+        let mut stream_infos = Vec::new();
+        data.write_index_streams(&mut out, &mut stream_infos).unwrap();
+        data.write_data_streams(&mut out, &mut stream_infos).unwrap();
+        let mut stats = Vec::new();
+        data.statistics(&mut stats);
+        (out.into_inner(), stats.iter().map(|s| s.to_proto()).collect())
+    }
+
+    #[test]
+    fn test_write_offsets() {
+        let config = Config::new().with_row_index_stride(4);
+        let values = ["pear", "apple", "", "zebra", "mango", "kiwi", "fig", "banana", "cherry", "date"];
+        let validity: Vec<bool> = (0..values.len()).map(|i| i % 4 != 1).collect();
+        let mut data: Vec<u8> = Vec::new();
+        let mut offsets: Vec<i32> = vec![0];
+        for x in &values {
+            data.extend(x.as_bytes());
+            offsets.push(data.len() as i32);
+        }
+
+        let mut expected = StringData::new(&Schema::String, &config, &mut 0);
+        expected.write("x");
+        for (x, &valid) in values.iter().zip(&validity) {
+            if valid { expected.write(x); } else { expected.write_null(); }
+        }
+        let mut actual = StringData::new(&Schema::String, &config, &mut 0);
+        actual.write("x");
+        actual.write_offsets(&data, &offsets, Some(&validity)).unwrap();
+        assert_eq!(serialize(&mut actual), serialize(&mut expected));
+
+        assert!(actual.write_offsets(&[0xff], &[0, 1], None).is_err());
+        for offsets in [&[0, -1][..], &[0, 2, 1], &[0, 3]] {
+            assert_eq!(actual.write_offsets(b"ab", offsets, None).err().unwrap().kind(), ErrorKind::InvalidInput);
+        }
+        assert_eq!(actual.write_offsets(b"ab", &[0, 1], Some(&[true, true])).err().unwrap().kind(), ErrorKind::InvalidInput);
+        // The offsets of null values are not used.
+        assert!(actual.write_offsets(b"ab", &[0, 1, -3], Some(&[true, false])).is_ok());
+    }
+}
@@ -6,7 +6,7 @@ use crate::writer::count_write::CountWrite;
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, SignedIntRLEv1, UnsignedIntRLEv1, IntRLEv1Position};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, TimestampStatistics};
-use crate::writer::data::common::{GenericData, BaseData, write_index};
+use crate::writer::data::common::{GenericData, BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE};
 
 
 pub struct TimestampData {
@@ -38,12 +38,12 @@ struct TimestampRowIndexEntry {
 }
 
 impl TimestampDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &TimestampDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.seconds.record(out);
-        self.nanos.record(out);
+        self.seconds.record(streams.seconds.stream(), out);
+        self.nanos.record(streams.nanos.stream(), out);
     }
 }
 
@@ -55,6 +55,12 @@ impl TimestampDataStreams {
             nanos: self.nanos.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.seconds.reset();
+        self.nanos.reset();
+    }
 }
 
 
@@ -144,13 +150,21 @@ impl GenericData for TimestampData {
 impl BaseData for TimestampData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = TimestampStatistics::new();
+        self.row_group_stats = TimestampStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Timestamp(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -206,6 +220,7 @@ impl BaseData for TimestampData {
         self.streams.present.estimated_size() 
             + self.streams.seconds.estimated_size() 
             + self.streams.nanos.estimated_size()
+            + self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE
     }
 
     fn verify_row_count(&self, expected_row_count: u64) {
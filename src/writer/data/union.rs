@@ -7,7 +7,7 @@ use crate::writer::count_write::CountWrite;
 use crate::writer::encoder::{BooleanRLE, BooleanRLEPosition, ByteRLE, ByteRLEPosition};
 use crate::writer::stripe::StreamInfo;
 use crate::writer::statistics::{Statistics, BaseStatistics, GenericStatistics};
-use crate::writer::data::common::{BaseData, write_index};
+use crate::writer::data::common::{BaseData, write_index, ESTIMATED_INDEX_ENTRY_SIZE};
 use crate::writer::data::{GenericData, Data};
 
 pub struct UnionData {
@@ -39,11 +39,11 @@ struct UnionRowIndexEntry {
 }
 
 impl UnionDataPosition {
-    pub fn record(&self, include_present: bool, out: &mut Vec<u64>) {
+    pub fn record(&self, streams: &UnionDataStreams, include_present: bool, out: &mut Vec<u64>) {
         if include_present {
-            self.present.record(out);
+            self.present.record(streams.present.stream(), out);
         }
-        self.tags.record(out);
+        self.tags.record(streams.tags.stream(), out);
     }
 }
 
@@ -54,6 +54,11 @@ impl UnionDataStreams {
             tags: self.tags.position(),
         }
     }
+
+    pub fn reset(&mut self) {
+        self.present.reset();
+        self.tags.reset();
+    }
 }
 
 impl UnionData {
@@ -139,13 +144,27 @@ impl GenericData for UnionData {
 impl BaseData for UnionData {
     fn column_id(&self) -> u32 { self.column_id }
 
+    fn reset(&mut self) {
+        self.streams.reset();
+        self.stripe_stats = GenericStatistics::new();
+        self.row_group_stats = GenericStatistics::new();
+        self.row_group_position = self.streams.position();
+        self.row_index_entries.clear();
+        for count in &mut self.child_counts {
+            *count = 0;
+        }
+        for child in &mut self.children {
+            child.reset();
+        }
+    }
+
     fn write_index_streams<W: Write>(&mut self, out: &mut CountWrite<W>, stream_infos_out: &mut Vec<StreamInfo>) -> Result<()> {
         self.finish_row_group();
         let mut row_index_entries: Vec<orc_proto::RowIndexEntry> = Vec::new();
         for entry in &self.row_index_entries {
             let mut row_index_entry = orc_proto::RowIndexEntry::new();
             let mut positions: Vec<u64> = Vec::new();
-            entry.position.record(self.stripe_stats.has_null(), &mut positions);
+            entry.position.record(&self.streams, self.stripe_stats.has_null(), &mut positions);
             row_index_entry.set_positions(positions);
             row_index_entry.set_statistics(Statistics::Generic(entry.stats).to_proto());
             row_index_entries.push(row_index_entry);
@@ -221,6 +240,7 @@ impl BaseData for UnionData {
         for child in &self.children {
             size += child.estimated_size();
         }
+        size += self.row_index_entries.len() * ESTIMATED_INDEX_ENTRY_SIZE;
         size
     }
 }
@@ -1,4 +1,4 @@
-use crate::writer::compression::Compression;
+use crate::writer::compression::{Compression, CompressionStream};
 use std::io::{Write, Result};
 
 use crate::writer::encoder::byte_rle::{ByteRLE, ByteRLEPosition};
@@ -19,8 +19,8 @@ pub struct BooleanRLEPosition {
 }
 
 impl BooleanRLEPosition {
-    pub fn record(&self, out: &mut Vec<u64>) {
-        self.inner.record(out);
+    pub fn record(&self, stream: &CompressionStream, out: &mut Vec<u64>) {
+        self.inner.record(stream, out);
         out.push(self.bits as u64);
     }
 }
@@ -34,6 +34,11 @@ impl BooleanRLE {
         }
     }
 
+    /// The underlying stream, against which positions are recorded.
+    pub fn stream(&self) -> &CompressionStream {
+        self.byte_rle.stream()
+    }
+
     pub fn position(&self) -> BooleanRLEPosition {
         BooleanRLEPosition {
             inner: self.byte_rle.position(),
@@ -61,7 +66,13 @@ impl BooleanRLE {
     }
 
     pub fn estimated_size(&self) -> usize {
-        self.byte_rle.estimated_size()
+        self.byte_rle.estimated_size() + (self.cnt > 0) as usize
+    }
+
+    pub fn reset(&mut self) {
+        self.buf = 0;
+        self.cnt = 0;
+        self.byte_rle.reset();
     }
 }
 
@@ -85,6 +96,7 @@ mod tests {
             let mut out: Vec<u8> = Vec::new();
             rle.finish(&mut out).unwrap();
             assert_eq!(out, expected_output);
+            rle.reset();
         }
     }
 }
@@ -16,8 +16,8 @@ pub struct ByteRLEPosition {
 }
 
 impl ByteRLEPosition {
-    pub fn record(&self, out: &mut Vec<u64>) {
-        self.inner.record(out);
+    pub fn record(&self, stream: &CompressionStream, out: &mut Vec<u64>) {
+        self.inner.record(stream, out);
         out.push(self.rle_offset);
     }
 }
@@ -32,6 +32,11 @@ impl ByteRLE {
         }
     }
 
+    /// The underlying stream, against which positions are recorded.
+    pub fn stream(&self) -> &CompressionStream {
+        &self.sink
+    }
+
     pub fn position(&self) -> ByteRLEPosition {
         ByteRLEPosition {
             inner: self.sink.position(),
@@ -93,7 +98,20 @@ impl ByteRLE {
     }
 
     pub fn estimated_size(&self) -> usize {
-        self.sink.estimated_size()
+        let pending = if self.run_len > 0 {
+            2
+        } else if self.buf_len > 0 {
+            1 + self.buf_len
+        } else {
+            0
+        };
+        self.sink.estimated_size() + self.sink.estimate_compressed(pending)
+    }
+
+    pub fn reset(&mut self) {
+        self.buf_len = 0;
+        self.run_len = 0;
+        self.sink.reset();
     }
 }
 
@@ -124,6 +142,7 @@ mod tests {
             let mut out: Vec<u8> = Vec::new();
             rle.finish(&mut out).unwrap();
             assert_eq!(out, expected_output);
+            rle.reset();
         }
     }
 }
\ No newline at end of file
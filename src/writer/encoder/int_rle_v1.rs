@@ -1,4 +1,5 @@
 use std::io::{Write, Result};
+use std::mem;
 use crate::writer::compression::{Compression, CompressionStream, CompressionStreamPosition};
 use super::varint::VarInt;
 
@@ -17,8 +18,8 @@ pub struct IntRLEv1Position {
 }
 
 impl IntRLEv1Position {
-    pub fn record(&self, out: &mut Vec<u64>) {
-        self.inner.record(out);
+    pub fn record(&self, stream: &CompressionStream, out: &mut Vec<u64>) {
+        self.inner.record(stream, out);
         out.push(self.rle_offset);
     }
 }
@@ -102,7 +103,23 @@ impl<T: VarInt> IntRLEv1<T> {
     }
 
     pub fn estimated_size(&self) -> usize {
-        self.sink.estimated_size()
+        // The group not yet written, assuming its values take their full width
+        let pending = if self.run_len > 0 {
+            2 + mem::size_of::<T>()
+        } else if !self.buf.is_empty() {
+            1 + self.buf.len() * mem::size_of::<T>()
+        } else {
+            0
+        };
+        self.sink.estimated_size() + self.sink.estimate_compressed(pending)
+    }
+
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.run_len = 0;
+        self.last_val = T::default();
+        self.delta = T::default();
+        self.sink.reset();
     }
 }
 
@@ -127,9 +144,18 @@ impl SignedIntRLEv1 {
         self.0.position()
     }
 
+    /// The underlying stream, against which positions are recorded.
+    pub fn stream(&self) -> &CompressionStream {
+        &self.0.sink
+    }
+
     pub fn estimated_size(&self) -> usize {
         self.0.estimated_size()
     }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
 }
 
 pub struct UnsignedIntRLEv1(IntRLEv1<u64>);
@@ -148,6 +174,11 @@ impl UnsignedIntRLEv1 {
         self.0.position()
     }
 
+    /// The underlying stream, against which positions are recorded.
+    pub fn stream(&self) -> &CompressionStream {
+        &self.0.sink
+    }
+
     pub fn finish<W: Write>(&mut self, w: &mut W) -> Result<()> {
         self.0.finish(w)
     }
@@ -155,6 +186,10 @@ impl UnsignedIntRLEv1 {
     pub fn estimated_size(&self) -> usize {
         self.0.estimated_size()
     }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
 }
 
 #[cfg(test)]
@@ -180,6 +215,7 @@ mod tests {
             let mut out: Vec<u8> = Vec::new();
             rle.finish(&mut out).unwrap();
             assert_eq!(out, expected_output);
+            rle.reset();
         }
     }
 
@@ -196,6 +232,7 @@ mod tests {
             let mut out: Vec<u8> = Vec::new();
             rle.finish(&mut out).unwrap();
             assert_eq!(out, expected_output);
+            rle.reset();
         }
     }
 }
\ No newline at end of file
@@ -130,6 +130,7 @@ mod tests {
             let mut out: Vec<u8> = Vec::new();
             stream.finish(&mut out).unwrap();
             assert_eq!(out, expected_output);
+            stream.reset();
         }
     }
 
@@ -148,6 +149,7 @@ mod tests {
             let mut out: Vec<u8> = Vec::new();
             stream.finish(&mut out).unwrap();
             assert_eq!(out, expected_output);
+            stream.reset();
         }
     }
 
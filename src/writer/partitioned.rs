@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs::{self, File};
+use std::io::{BufWriter, Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::schema::{Field, Schema};
+use crate::value::Value;
+use super::rolling::CompletedFile;
+use super::{Config, Writer};
+
+/// The directory name Hive uses for null or empty partition values
+const DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+struct OpenPartition {
+    dir: PathBuf,
+    path: PathBuf,
+    writer: Writer<BufWriter<File>>,
+    num_rows: u64,
+}
+
+/// Writes rows of a struct schema to a Hive-style partitioned directory tree, e.g.
+/// `root/dt=2024-01-31/country=NZ/part-00000.orc`. The values of the partition columns choose the
+/// directory, and are left out of the stored rows. At most a fixed number of files are open at
+/// once: beyond that, the least recently written one is finished, and a partition that receives
+/// more rows afterwards gets a new file. Files are numbered within each directory after any
+/// existing `part-NNNNN.orc` files, so writing into an existing tree adds files rather than
+/// replacing them. Rows are routed one at a time; there is no column batch path, since the columns
+/// of a filled `Data` cannot be read back to find each row's partition.
+///
+/// Since every reopened partition gets a new file, input that interleaves the rows of more
+/// partitions than the open file limit is written as many small files, down to one per row. Sort
+/// or cluster the rows by the partition columns first, or raise `with_max_open_writers`.
+pub struct PartitionedWriter {
+    root: PathBuf,
+    partition_columns: Vec<(usize, Field)>,
+    data_columns: Vec<usize>,
+    data_schema: Schema,
+    config: Config,
+    max_open_writers: usize,
+    // Least recently written first
+    open: Vec<OpenPartition>,
+    num_files: HashMap<PathBuf, usize>,
+    completed: Vec<CompletedFile<PathBuf>>,
+}
+
+impl PartitionedWriter {
+    /// Creates a writer of rows of `schema` under `root`, partitioned by the named top-level fields
+    /// in the given order. Partition columns must be of a primitive type other than binary or
+    /// timestamp. Each file is written with `config`; sharing a `MemoryManager` through it bounds
+    /// the memory of all open files together.
+    pub fn new<P: AsRef<Path>>(root: P, schema: &Schema, partition_by: &[&str], config: Config) -> Result<Self> {
+        let fields = match schema {
+            Schema::Struct(fields) => fields,
+            _ => return Err(Error::new(ErrorKind::InvalidInput, "Partitioned rows must have a struct schema")),
+        };
+        let mut partition_columns: Vec<(usize, Field)> = Vec::new();
+        for name in partition_by {
+            let index = fields.iter().position(|Field(n, _)| n == name)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("No field named {:?}", name)))?;
+            match fields[index].1 {
+                Schema::Boolean | Schema::Short | Schema::Int | Schema::Long | Schema::Date | Schema::Float
+                | Schema::Double | Schema::Decimal(_, _) | Schema::String | Schema::Char(_) | Schema::VarChar(_) => {}
+                _ => return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("Field {:?} cannot be a partition column", name))),
+            }
+            partition_columns.push((index, fields[index].clone()));
+        }
+        let data_columns: Vec<usize> = (0..fields.len())
+            .filter(|i| !partition_columns.iter().any(|(j, _)| j == i))
+            .collect();
+        let data_schema = Schema::Struct(data_columns.iter().map(|&i| fields[i].clone()).collect());
+        Ok(PartitionedWriter {
+            root: root.as_ref().to_owned(),
+            partition_columns,
+            data_columns,
+            data_schema,
+            config,
+            max_open_writers: 100,
+            open: Vec::new(),
+            num_files: HashMap::new(),
+            completed: Vec::new(),
+        })
+    }
+
+    /// Sets the maximum number of files open at once (100 by default). Rows of more partitions than
+    /// this should be clustered by partition, or each reopened partition starts another small file.
+    pub fn with_max_open_writers(mut self, max_open_writers: usize) -> Self {
+        assert!(max_open_writers > 0, "max_open_writers must be positive");
+        self.max_open_writers = max_open_writers;
+        self
+    }
+
+    /// The schema of the stored rows, without the partition columns.
+    pub fn data_schema(&self) -> &Schema {
+        &self.data_schema
+    }
+
+    pub fn write_row(&mut self, row: &Value) -> Result<()> {
+        let values = match row {
+            Value::Struct(values) if values.len() == self.data_columns.len() + self.partition_columns.len() => values,
+            _ => return Err(Error::new(ErrorKind::InvalidInput, "Row does not match the schema")),
+        };
+        let mut dir = self.root.clone();
+        for (index, Field(name, schema)) in &self.partition_columns {
+            dir.push(format!("{}={}", escape(name), partition_value(name, schema, &values[*index])?));
+        }
+        let data_row = Value::Struct(self.data_columns.iter().map(|&i| values[i].clone()).collect());
+        let partition = self.partition(dir)?;
+        partition.writer.write_row(&data_row)?;
+        partition.num_rows += 1;
+        Ok(())
+    }
+
+    /// Writes a batch of rows.
+    pub fn write_rows(&mut self, rows: &[Value]) -> Result<()> {
+        for row in rows {
+            self.write_row(row)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the open file of the partition in `dir`, opening one if needed, and marks it as the
+    /// most recently written.
+    fn partition(&mut self, dir: PathBuf) -> Result<&mut OpenPartition> {
+        match self.open.iter().position(|p| p.dir == dir) {
+            Some(i) => {
+                let partition = self.open.remove(i);
+                self.open.push(partition);
+            }
+            None => {
+                if self.open.len() == self.max_open_writers {
+                    let partition = self.open.remove(0);
+                    self.finish_partition(partition)?;
+                }
+                fs::create_dir_all(&dir)?;
+                let num_files = match self.num_files.entry(dir.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(next_file_number(&dir)?),
+                };
+                let path = dir.join(format!("part-{:05}.orc", num_files));
+                *num_files += 1;
+                let writer = Writer::create(&path, &self.data_schema, self.config.clone())?;
+                self.open.push(OpenPartition { dir, path, writer, num_rows: 0 });
+            }
+        }
+        Ok(self.open.last_mut().unwrap())
+    }
+
+    fn finish_partition(&mut self, partition: OpenPartition) -> Result<()> {
+        let (_, statistics) = partition.writer.finish_with_statistics()?;
+        self.completed.push(CompletedFile { name: partition.path, num_rows: partition.num_rows, statistics });
+        Ok(())
+    }
+
+    /// The files completed so far.
+    pub fn completed(&self) -> &[CompletedFile<PathBuf>] {
+        &self.completed
+    }
+
+    /// Finishes all open files, and returns all completed files in the order they were finished.
+    pub fn finish(mut self) -> Result<Vec<CompletedFile<PathBuf>>> {
+        for partition in std::mem::take(&mut self.open) {
+            self.finish_partition(partition)?;
+        }
+        Ok(self.completed)
+    }
+}
+
+/// Returns the number after the highest numbered `part-NNNNN.orc` file in `dir`, or 0 if there is none.
+fn next_file_number(dir: &Path) -> Result<usize> {
+    let mut next = 0;
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let number = name.to_str()
+            .and_then(|name| name.strip_prefix("part-"))
+            .and_then(|name| name.strip_suffix(".orc"))
+            .and_then(|number| number.parse::<usize>().ok());
+        if let Some(number) = number {
+            next = next.max(number + 1);
+        }
+    }
+    Ok(next)
+}
+
+/// Formats a partition value as it appears in a directory name.
+fn partition_value(name: &str, schema: &Schema, value: &Value) -> Result<String> {
+    let formatted = match (schema, value) {
+        (_, Value::Null) => return Ok(DEFAULT_PARTITION.to_owned()),
+        (Schema::Boolean, Value::Bool(x)) => x.to_string(),
+        (Schema::Short, Value::Int(x)) | (Schema::Int, Value::Int(x)) | (Schema::Long, Value::Int(x)) => x.to_string(),
+        (Schema::Float, Value::Double(x)) | (Schema::Double, Value::Double(x)) => x.to_string(),
+        // The scale must match for equal values to share a directory, and bounds the power of ten.
+        (Schema::Decimal(_, scale), Value::Decimal(x, s)) if s == scale && *s <= 38 => format_decimal(*x, *s),
+        (Schema::Date, Value::Date(days)) => format_date(*days),
+        (Schema::String, Value::String(x)) | (Schema::Char(_), Value::String(x))
+        | (Schema::VarChar(_), Value::String(x)) => x.clone(),
+        _ => return Err(Error::new(ErrorKind::InvalidInput,
+            format!("Invalid {} value for partition column {:?}", value.kind(), name))),
+    };
+    if formatted.is_empty() {
+        return Ok(DEFAULT_PARTITION.to_owned());
+    }
+    Ok(escape(&formatted))
+}
+
+/// Escapes the characters that Hive does not allow in path names as `%XX`.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii_control() || "\"#%'*/:=?\\{[]^".contains(c) {
+            out.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn format_decimal(x: i128, scale: u32) -> String {
+    let modulus = 10i128.pow(scale);
+    let sign = if x < 0 { "-" } else { "" };
+    let (quo, rem) = ((x / modulus).abs(), (x % modulus).abs());
+    if scale == 0 {
+        format!("{}{}", sign, quo)
+    } else {
+        format!("{}{}.{:0width$}", sign, quo, rem, width = scale as usize)
+    }
+}
+
+/// Formats days since the UNIX epoch as `YYYY-MM-DD`.
+fn format_date(days: i32) -> String {
+    // Converts to the proleptic Gregorian calendar in 400-year eras starting on March 1st.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::Reader;
+
+    #[test]
+    fn test_partitioned_writer() {
+        let root = std::env::temp_dir().join(format!("orc-format-partitioned-{}", std::process::id()));
+        let schema = Schema::Struct(vec![
+            Field("id".to_owned(), Schema::Long),
+            Field("country".to_owned(), Schema::String),
+            Field("dt".to_owned(), Schema::Date),
+        ]);
+        let mut writer = PartitionedWriter::new(&root, &schema, &["dt", "country"], Config::new()).unwrap()
+            .with_max_open_writers(2);
+        assert_eq!(writer.data_schema(), &Schema::Struct(vec![Field("id".to_owned(), Schema::Long)]));
+        let countries = [Value::String("NZ".to_owned()), Value::String("a/b".to_owned()), Value::Null];
+        let rows: Vec<Value> = (0..30).map(|i| Value::Struct(vec![
+            Value::Int(i),
+            countries[i as usize % 3].clone(),
+            Value::Date(19753 + (i as i32 % 2)),
+        ])).collect();
+        writer.write_rows(&rows).unwrap();
+        assert!(writer.write_row(&Value::Struct(vec![Value::Int(0), Value::Int(1), Value::Date(0)])).is_err());
+        let files = writer.finish().unwrap();
+
+        // Six partitions, with every row going to a different one than the row before.
+        assert_eq!(files.len(), 30);
+        assert_eq!(files.iter().map(|f| f.num_rows).sum::<u64>(), 30);
+        let dir = root.join("dt=2024-01-31").join("country=a%2Fb");
+        let mut ids: Vec<Value> = Vec::new();
+        for n in 0..5 {
+            let file = File::open(dir.join(format!("part-{:05}.orc", n))).unwrap();
+            ids.extend(Reader::new(file).unwrap().rows().unwrap());
+        }
+        let expected: Vec<Value> = (0..30).filter(|i| i % 3 == 1 && i % 2 == 0)
+            .map(|i| Value::Struct(vec![Value::Int(i)]))
+            .collect();
+        assert_eq!(ids, expected);
+        assert!(root.join("dt=2024-02-01").join("country=__HIVE_DEFAULT_PARTITION__").join("part-00000.orc").exists());
+
+        // Writing into the existing tree continues the numbering instead of replacing files.
+        let mut writer = PartitionedWriter::new(&root, &schema, &["dt", "country"], Config::new()).unwrap();
+        writer.write_rows(&rows[..2]).unwrap();
+        let files = writer.finish().unwrap();
+        let dir = root.join("dt=2024-01-31").join("country=NZ");
+        assert_eq!(files[0].name, dir.join("part-00005.orc"));
+        assert_eq!(files[1].name, root.join("dt=2024-02-01").join("country=a%2Fb").join("part-00005.orc"));
+        let rows: Vec<Value> = (0..6).flat_map(|n| {
+            let file = File::open(dir.join(format!("part-{:05}.orc", n))).unwrap();
+            Reader::new(file).unwrap().rows().unwrap()
+        }).collect();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[5], Value::Struct(vec![Value::Int(0)]));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(19753), "2024-01-31");
+        assert_eq!(format_date(11016), "2000-02-29");
+        assert_eq!(format_date(-1), "1969-12-31");
+        assert_eq!(format_decimal(-1205, 2), "-12.05");
+        let schema = Schema::Decimal(10, 2);
+        assert_eq!(partition_value("p", &schema, &Value::Decimal(-1205, 2)).unwrap(), "-12.05");
+        assert_eq!(partition_value("p", &schema, &Value::Decimal(1205, 3)).err().unwrap().kind(), ErrorKind::InvalidInput);
+        let schema = Schema::Decimal(38, 40);
+        assert_eq!(partition_value("p", &schema, &Value::Decimal(1, 40)).err().unwrap().kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{Result, Write};
+
+use crate::record::OrcRecord;
+use crate::value::Value;
+use super::data::{BaseData, Data};
+use super::statistics::Statistics;
+use super::Writer;
+
+/// A file completed by a `RollingWriter`.
+#[derive(Debug)]
+pub struct CompletedFile<N> {
+    /// The name given by the factory when the file was opened
+    pub name: N,
+    pub num_rows: u64,
+    /// The statistics of the file's columns, indexed by column id
+    pub statistics: Vec<Statistics>,
+}
+
+/// Writes rows to a sequence of files, moving on to the next file once the current one reaches a
+/// maximum number of rows or bytes. Files are opened as needed by a factory, which is given the
+/// index of the file (counting from 0) and returns a name for it along with its writer, e.g.
+/// `part-00001.orc` and a writer from `Writer::create`. A file is only ever ended at a stripe
+/// boundary, by finishing its writer.
+pub struct RollingWriter<N, W: Write, F> {
+    factory: F,
+    current: Option<(N, Writer<W>, u64)>,
+    num_files: usize,
+    max_rows: Option<u64>,
+    max_bytes: Option<u64>,
+    completed: Vec<CompletedFile<N>>,
+}
+
+impl<N, W: Write, F: FnMut(usize) -> Result<(N, Writer<W>)>> RollingWriter<N, W, F> {
+    pub fn new(factory: F) -> Self {
+        RollingWriter {
+            factory,
+            current: None,
+            num_files: 0,
+            max_rows: None,
+            max_bytes: None,
+            completed: Vec::new(),
+        }
+    }
+
+    /// Ends a file once it has at least `max_rows` rows. With rows written one at a time, no file
+    /// has more.
+    pub fn with_max_rows(mut self, max_rows: u64) -> Self {
+        assert!(max_rows > 0, "max_rows must be positive");
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// Ends a file once its written stripes and the estimated size of its current stripe add up to
+    /// at least `max_bytes`. Since the estimate is approximate and the tail comes on top, files can
+    /// be slightly larger; a smaller stripe size gives a closer fit. With a background flush, 
+    /// stripes still queued for writing are not counted yet, so files can grow by up to that many 
+    /// stripes more.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        assert!(max_bytes > 0, "max_bytes must be positive");
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    fn writer(&mut self) -> Result<&mut Writer<W>> {
+        if self.current.is_none() {
+            let (name, writer) = (self.factory)(self.num_files)?;
+            self.num_files += 1;
+            self.current = Some((name, writer, 0));
+        }
+        Ok(&mut self.current.as_mut().unwrap().1)
+    }
+
+    /// The columns of the current file, to be filled with a batch of rows before calling
+    /// `write_batch`. Opens a new file if there is none.
+    pub fn data(&mut self) -> Result<&mut Data> {
+        Ok(self.writer()?.data())
+    }
+
+    pub fn write_batch(&mut self, num_rows: u64) -> Result<()> {
+        self.writer()?.write_batch(num_rows)?;
+        let (_, writer, file_rows) = self.current.as_mut().unwrap();
+        *file_rows += num_rows;
+        let full_rows = self.max_rows.is_some_and(|max| *file_rows >= max);
+        let full_bytes = self.max_bytes.is_some_and(|max| {
+            writer.bytes_written() + writer.current_stripe.data.estimated_size() as u64 >= max
+        });
+        if full_rows || full_bytes {
+            self.finish_file()?;
+        }
+        Ok(())
+    }
+
+    pub fn write_row(&mut self, row: &Value) -> Result<()> {
+        let data = self.data()?;
+        data.check_value(row)?;
+        data.write_value(row)?;
+        self.write_batch(1)
+    }
+
+    pub fn write_record<T: OrcRecord>(&mut self, record: &T) -> Result<()> {
+        record.write_to(self.data()?.unwrap_struct());
+        self.write_batch(1)
+    }
+
+    /// Finishes the current file, if any, so that the next row starts a new one.
+    pub fn finish_file(&mut self) -> Result<()> {
+        if let Some((name, writer, num_rows)) = self.current.take() {
+            let (_, statistics) = writer.finish_with_statistics()?;
+            self.completed.push(CompletedFile { name, num_rows, statistics });
+        }
+        Ok(())
+    }
+
+    /// The files completed so far.
+    pub fn completed(&self) -> &[CompletedFile<N>] {
+        &self.completed
+    }
+
+    /// Finishes the current file, and returns all completed files in order.
+    pub fn finish(mut self) -> Result<Vec<CompletedFile<N>>> {
+        self.finish_file()?;
+        Ok(self.completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::Reader;
+    use crate::schema::{Field, Schema};
+    use crate::writer::Config;
+    use crate::writer::statistics::BaseStatistics;
+    use std::fs::{self, File};
+
+    #[test]
+    fn test_rolling_writer() {
+        let dir = std::env::temp_dir().join(format!("orc-format-rolling-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let schema = Schema::Struct(vec![Field("a".to_owned(), Schema::Long)]);
+        let row = |i: i64| Value::Struct(vec![Value::Int(i)]);
+
+        let mut writer = RollingWriter::new(|i| {
+            let path = dir.join(format!("part-{:05}.orc", i));
+            Ok((path.clone(), Writer::create(path, &schema, Config::new())?))
+        }).with_max_rows(400);
+        for i in 0..1000 {
+            writer.write_row(&row(i)).unwrap();
+        }
+        assert_eq!(writer.completed().len(), 2);
+        let files = writer.finish().unwrap();
+        assert_eq!(files.iter().map(|f| f.num_rows).collect::<Vec<_>>(), vec![400, 400, 200]);
+        assert_eq!(files[2].name, dir.join("part-00002.orc"));
+        assert_eq!(files[1].statistics[1].unwrap_long().min, Some(400));
+        assert_eq!(files[1].statistics[1].num_values(), 400);
+        let mut rows: Vec<Value> = Vec::new();
+        for file in &files {
+            rows.extend(Reader::new(File::open(&file.name).unwrap()).unwrap().rows().unwrap());
+        }
+        assert_eq!(rows, (0..1000).map(row).collect::<Vec<_>>());
+
+        // With a byte limit, each file ends once its estimated size reaches the limit.
+        let config = Config::new().with_stripe_size(4000);
+        let mut writer = RollingWriter::new(|i| Ok((i, Writer::new(Vec::new(), &schema, config.clone())?)))
+            .with_max_bytes(10000);
+        for i in 0..20000 {
+            writer.write_row(&row(i * 7919)).unwrap();
+        }
+        let files = writer.finish().unwrap();
+        assert!(files.len() > 1);
+        assert_eq!(files.iter().map(|f| f.num_rows).sum::<u64>(), 20000);
+
+        // Stripes written in the background count towards the limit too.
+        let mut writer = RollingWriter::new(|i| {
+            Ok((i, Writer::new(Vec::new(), &schema, config.clone())?.with_background_flush(2)))
+        }).with_max_bytes(10000);
+        for i in 0..20000 {
+            writer.write_row(&row(i * 7919)).unwrap();
+        }
+        let files = writer.finish().unwrap();
+        assert!(files.len() > 1);
+        assert_eq!(files.iter().map(|f| f.num_rows).sum::<u64>(), 20000);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -9,6 +9,7 @@ pub use double::DoubleStatistics;
 pub use decimal::DecimalStatistics;
 pub use timestamp::TimestampStatistics;
 pub use binary::BinaryStatistics;
+pub use collection::CollectionStatistics;
 
 mod common;
 mod boolean;
@@ -19,6 +20,7 @@ mod double;
 mod decimal;
 mod timestamp;
 mod binary;
+mod collection;
 
 #[derive(Debug, Clone)]
 pub enum Statistics {
@@ -29,6 +31,7 @@ pub enum Statistics {
     Timestamp(TimestampStatistics),
     String(StringStatistics),
     Binary(BinaryStatistics),
+    Collection(CollectionStatistics),
     Generic(GenericStatistics),
 }
 
@@ -61,6 +64,10 @@ impl Statistics {
         if let Statistics::Timestamp(x) = self { x } else { panic!("invalid argument to unwrap_timestamp"); }
     }
 
+    pub fn unwrap_collection(&self) -> &CollectionStatistics { 
+        if let Statistics::Collection(x) = self { x } else { panic!("invalid argument to unwrap_collection"); }
+    }
+
     pub fn unwrap_generic(&self) -> &GenericStatistics { 
         if let Statistics::Generic(x) = self { x } else { panic!("invalid argument to unwrap_struct"); }
     }
@@ -104,8 +111,20 @@ impl Statistics {
             }
             Statistics::String(string_statistics) => {
                 let mut str_stat = orc_proto::StringStatistics::new();
-                if let Some(x) = &string_statistics.min { str_stat.set_minimum(x.clone()); }
-                if let Some(x) = &string_statistics.max { str_stat.set_maximum(x.clone()); }
+                if let Some(x) = &string_statistics.min { 
+                    if string_statistics.min_truncated {
+                        str_stat.set_lowerBound(x.clone());
+                    } else {
+                        str_stat.set_minimum(x.clone());
+                    }
+                }
+                if let Some(x) = &string_statistics.max { 
+                    if string_statistics.max_truncated {
+                        str_stat.set_upperBound(x.clone());
+                    } else {
+                        str_stat.set_maximum(x.clone());
+                    }
+                }
                 str_stat.set_sum(string_statistics.sum_lengths as i64);
                 stat.set_stringStatistics(str_stat);
             }
@@ -114,10 +133,127 @@ impl Statistics {
                 bin_stat.set_sum(binary_statistics.sum_lengths as i64);
                 stat.set_binaryStatistics(bin_stat);
             }
+            Statistics::Collection(c) => {
+                let mut coll_stat = orc_proto::CollectionStatistics::new();
+                if let Some(x) = c.min_children { coll_stat.set_minChildren(x); }
+                if let Some(x) = c.max_children { coll_stat.set_maxChildren(x); }
+                coll_stat.set_totalChildren(c.total_children);
+                stat.set_collectionStatistics(coll_stat);
+            }
             Statistics::Generic(_) => {}
         }
         stat
     }
+
+    /// Reads statistics written by `to_proto` into a value of the same kind as `like`. Missing or
+    /// unparseable fields are treated as unknown; where values are present but the min or max is
+    /// unknown, the bounds are marked as unknown, so that merging drops them.
+    pub fn from_proto(proto: &orc_proto::ColumnStatistics, like: &Statistics) -> Statistics {
+        let num_present = proto.get_numberOfValues();
+        // Only whether there were any nulls is recorded, not how many.
+        let num_values = num_present + proto.get_hasNull() as u64;
+        let unknown = |min_known: bool, max_known: bool| num_present > 0 && !(min_known && max_known);
+        match like {
+            Statistics::Boolean(_) => {
+                let num_true = proto.get_bucketStatistics().get_count().first().copied().unwrap_or(0);
+                Statistics::Boolean(BooleanStatistics {
+                    num_values,
+                    num_true,
+                    num_false: num_present.saturating_sub(num_true),
+                })
+            }
+            Statistics::Long(_) => {
+                let s = proto.get_intStatistics();
+                Statistics::Long(LongStatistics {
+                    num_values,
+                    num_present,
+                    min: if s.has_minimum() { Some(s.get_minimum()) } else { None },
+                    max: if s.has_maximum() { Some(s.get_maximum()) } else { None },
+                    sum: if s.has_sum() { Some(s.get_sum()) } else { None },
+                    bounds_unknown: unknown(s.has_minimum(), s.has_maximum()),
+                })
+            }
+            Statistics::Decimal(d) => {
+                let s = proto.get_decimalStatistics();
+                let min = if s.has_minimum() { d.parse_bound(s.get_minimum(), false) } else { None };
+                let max = if s.has_maximum() { d.parse_bound(s.get_maximum(), true) } else { None };
+                Statistics::Decimal(DecimalStatistics {
+                    scale: d.scale,
+                    num_values,
+                    num_present,
+                    min,
+                    max,
+                    sum: if s.has_sum() { d.parse(s.get_sum()) } else { None },
+                    bounds_unknown: unknown(min.is_some(), max.is_some()),
+                })
+            }
+            Statistics::Timestamp(_) => {
+                let s = proto.get_timestampStatistics();
+                Statistics::Timestamp(TimestampStatistics {
+                    num_values,
+                    num_present,
+                    min_epoch_millis: if s.has_minimum() { Some(s.get_minimum()) } else { None },
+                    max_epoch_millis: if s.has_maximum() { Some(s.get_maximum()) } else { None },
+                    bounds_unknown: unknown(s.has_minimum(), s.has_maximum()),
+                })
+            }
+            Statistics::Double(_) => {
+                let s = proto.get_doubleStatistics();
+                Statistics::Double(DoubleStatistics {
+                    num_values,
+                    num_present,
+                    min: if s.has_minimum() { Some(s.get_minimum()) } else { None },
+                    max: if s.has_maximum() { Some(s.get_maximum()) } else { None },
+                    sum: s.get_sum(),
+                    bounds_unknown: unknown(s.has_minimum(), s.has_maximum()),
+                })
+            }
+            Statistics::String(_) => {
+                let s = proto.get_stringStatistics();
+                let bound = |has_exact: bool, exact: &str, has_bound: bool, bound: &str| {
+                    if has_exact {
+                        (Some(exact.to_owned()), false)
+                    } else if has_bound {
+                        (Some(bound.to_owned()), true)
+                    } else {
+                        (None, false)
+                    }
+                };
+                let (min, min_truncated) = bound(s.has_minimum(), s.get_minimum(), s.has_lowerBound(), s.get_lowerBound());
+                let (max, max_truncated) = bound(s.has_maximum(), s.get_maximum(), s.has_upperBound(), s.get_upperBound());
+                let bounds_unknown = unknown(min.is_some(), max.is_some());
+                Statistics::String(StringStatistics {
+                    num_values,
+                    num_present,
+                    min,
+                    max,
+                    min_truncated,
+                    max_truncated,
+                    sum_lengths: s.get_sum() as u64,
+                    bounds_unknown,
+                })
+            }
+            Statistics::Binary(_) => {
+                Statistics::Binary(BinaryStatistics {
+                    num_values,
+                    num_present,
+                    sum_lengths: proto.get_binaryStatistics().get_sum() as u64,
+                })
+            }
+            Statistics::Collection(_) => {
+                let s = proto.get_collectionStatistics();
+                Statistics::Collection(CollectionStatistics {
+                    num_values,
+                    num_present,
+                    min_children: if s.has_minChildren() { Some(s.get_minChildren()) } else { None },
+                    max_children: if s.has_maxChildren() { Some(s.get_maxChildren()) } else { None },
+                    total_children: s.get_totalChildren(),
+                    bounds_unknown: unknown(s.has_minChildren(), s.has_maxChildren()),
+                })
+            }
+            Statistics::Generic(_) => Statistics::Generic(GenericStatistics { num_values, num_present }),
+        }
+    }
 }
 
 impl BaseStatistics for Statistics {
@@ -130,6 +266,7 @@ impl BaseStatistics for Statistics {
             Statistics::Timestamp(x) => x.update_null(),
             Statistics::String(x) => x.update_null(),
             Statistics::Binary(x) => x.update_null(),
+            Statistics::Collection(x) => x.update_null(),
             Statistics::Generic(x) => x.update_null(),
         }
     }
@@ -143,6 +280,7 @@ impl BaseStatistics for Statistics {
             Statistics::Timestamp(x) => x.num_values(),
             Statistics::String(x) => x.num_values(),
             Statistics::Binary(x) => x.num_values(),
+            Statistics::Collection(x) => x.num_values(),
             Statistics::Generic(x) => x.num_values(),
         }
     }
@@ -156,6 +294,7 @@ impl BaseStatistics for Statistics {
             Statistics::Timestamp(x) => x.num_present(),
             Statistics::String(x) => x.num_present(),
             Statistics::Binary(x) => x.num_present(),
+            Statistics::Collection(x) => x.num_present(),
             Statistics::Generic(x) => x.num_present(),
         }
     }
@@ -169,6 +308,7 @@ impl BaseStatistics for Statistics {
             Statistics::Timestamp(x) => x.merge(rhs.unwrap_timestamp()),
             Statistics::String(x) => x.merge(rhs.unwrap_string()),
             Statistics::Binary(x) => x.merge(rhs.unwrap_binary()),
+            Statistics::Collection(x) => x.merge(rhs.unwrap_collection()),
             Statistics::Generic(x) => x.merge(rhs.unwrap_generic()),
         }
     }
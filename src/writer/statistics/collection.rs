@@ -0,0 +1,134 @@
+use super::common::BaseStatistics;
+
+#[derive(Debug, Copy, Clone)]
+pub struct CollectionStatistics {
+    pub num_values: u64,
+    pub num_present: u64,
+    pub min_children: Option<u64>,
+    pub max_children: Option<u64>,
+    pub total_children: u64,
+    /// Whether some values have no known bounds, as in statistics read without a min or max, in
+    /// which case the min and max are left out
+    pub bounds_unknown: bool,
+}
+
+fn merge_min(x: &mut Option<u64>, y: Option<u64>) {
+    if let Some(yv) = y {
+        if let Some(xv) = x {
+            if yv < *xv {
+                *x = y;
+            }
+        } else {
+            *x = y;
+        }
+    }
+}
+
+fn merge_max(x: &mut Option<u64>, y: Option<u64>) {
+    if let Some(yv) = y {
+        if let Some(xv) = x {
+            if yv > *xv {
+                *x = y;
+            }
+        } else {
+            *x = y;
+        }
+    }
+}
+
+impl CollectionStatistics {
+    pub fn new() -> Self {
+        Self {
+            num_values: 0,
+            num_present: 0,
+            min_children: None,
+            max_children: None,
+            total_children: 0,
+            bounds_unknown: false,
+        }
+    }
+
+    pub fn update(&mut self, num_children: u64) {
+        self.num_values += 1;
+        self.num_present += 1;
+        merge_min(&mut self.min_children, Some(num_children));
+        merge_max(&mut self.max_children, Some(num_children));
+        self.total_children += num_children;
+    }
+}
+
+impl BaseStatistics for CollectionStatistics {
+    fn update_null(&mut self) {
+        self.num_values += 1;
+    }
+
+    fn num_values(&self) -> u64 { self.num_values }
+
+    fn num_present(&self) -> u64 { self.num_present }
+
+    fn merge(&mut self, rhs: &Self) {
+        self.num_values += rhs.num_values;
+        self.num_present += rhs.num_present;
+        merge_min(&mut self.min_children, rhs.min_children);
+        merge_max(&mut self.max_children, rhs.max_children);
+        self.total_children += rhs.total_children;
+        self.bounds_unknown |= rhs.bounds_unknown;
+        if self.bounds_unknown {
+            self.min_children = None;
+            self.max_children = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+    use crate::value::Value;
+    use crate::writer::Config;
+    use crate::writer::data::{BaseData, Data};
+    use crate::writer::statistics::Statistics;
+
+    /// Writes `values` as one row group, and returns the column's stripe statistics.
+    fn statistics(schema: Schema, values: &[Value]) -> CollectionStatistics {
+        let config = Config::new().with_row_index_stride(values.len() as u32);
+        let mut data = Data::new(&schema, &config, &mut 0);
+        for value in values {
+            data.write_value(value).unwrap();
+        }
+        let mut out: Vec<Statistics> = Vec::new();
+        data.statistics(&mut out);
+        *out[0].unwrap_collection()
+    }
+
+    #[test]
+    fn test_list_and_map() {
+        let list = |n: i64| Value::List((0..n).map(Value::Int).collect());
+        let stats = statistics(Schema::List(Box::new(Schema::Long)), &[list(3), Value::Null, list(0), list(5)]);
+        assert_eq!((stats.num_values, stats.num_present), (4, 3));
+        assert_eq!((stats.min_children, stats.max_children, stats.total_children), (Some(0), Some(5), 8));
+
+        let map = |n: i64| Value::Map((0..n).map(|i| (Value::Int(i), Value::Bool(true))).collect());
+        let schema = Schema::Map(Box::new(Schema::Long), Box::new(Schema::Boolean));
+        let stats = statistics(schema, &[map(2), map(7), Value::Null]);
+        assert_eq!((stats.num_values, stats.num_present), (3, 2));
+        assert_eq!((stats.min_children, stats.max_children, stats.total_children), (Some(2), Some(7), 9));
+
+        let proto = Statistics::Collection(stats).to_proto();
+        assert_eq!(proto.get_numberOfValues(), 2);
+        assert!(proto.get_hasNull());
+        assert_eq!(proto.get_collectionStatistics().get_minChildren(), 2);
+        assert_eq!(proto.get_collectionStatistics().get_maxChildren(), 7);
+        assert_eq!(proto.get_collectionStatistics().get_totalChildren(), 9);
+    }
+
+    #[test]
+    fn test_nulls_only() {
+        let stats = statistics(Schema::List(Box::new(Schema::Long)), &[Value::Null, Value::Null]);
+        assert_eq!((stats.num_values, stats.num_present), (2, 0));
+        assert_eq!((stats.min_children, stats.max_children, stats.total_children), (None, None, 0));
+        let proto = Statistics::Collection(stats).to_proto();
+        assert!(!proto.get_collectionStatistics().has_minChildren());
+        assert!(!proto.get_collectionStatistics().has_maxChildren());
+    }
+}
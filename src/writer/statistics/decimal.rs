@@ -1,3 +1,4 @@
+use std::convert::TryFrom;
 use std::fmt::Write;
 use super::common::BaseStatistics;
 
@@ -9,6 +10,9 @@ pub struct DecimalStatistics {
     pub min: Option<i128>,
     pub max: Option<i128>,
     pub sum: Option<i128>,
+    /// Whether some values have no known bounds, as in statistics read without a min or max, in
+    /// which case the min and max are left out
+    pub bounds_unknown: bool,
 }
 
 fn merge_min(x: &mut Option<i128>, y: Option<i128>) {
@@ -56,6 +60,7 @@ impl DecimalStatistics {
             min: None,
             max: None,
             sum: Some(0),
+            bounds_unknown: false,
         }
     }
 
@@ -82,6 +87,65 @@ impl DecimalStatistics {
         }
         out
     }
+
+    /// Parses a decimal as written by `format`, rescaled to this column's scale. Returns None if 
+    /// the value has more fractional digits than the scale.
+    pub fn parse(&self, s: &str) -> Option<i128> {
+        match self.parse_scaled(s)? {
+            (val, 0) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Like `parse`, but rounds extra fractional digits outward, up for an upper bound and down for 
+    /// a lower bound, so that the result is still a bound.
+    pub fn parse_bound(&self, s: &str, upper: bool) -> Option<i128> {
+        match self.parse_scaled(s)? {
+            (val, rest) if upper && rest > 0 => val.checked_add(1),
+            (val, rest) if !upper && rest < 0 => val.checked_sub(1),
+            (val, _) => Some(val),
+        }
+    }
+
+    /// Parses a decimal, which may have an exponent as in `1.5E+3`, into its value at this column's 
+    /// scale truncated towards zero, along with the sign of the digits cut off (or 0 if none were).
+    fn parse_scaled(&self, s: &str) -> Option<(i128, i32)> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+            None => (s, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int.len() + frac.len() == 0 || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let digits = format!("{}{}", int, frac);
+        let digits = digits.trim_start_matches('0');
+        if digits.len() > 38 {
+            return None;
+        }
+        let mut val: i128 = if digits.is_empty() { 0 } else { digits.parse().ok()? };
+        let mut rest = 0;
+        let shift = exponent.checked_sub(frac.len() as i64)?.checked_add(self.scale as i64)?;
+        if val != 0 && shift >= 0 {
+            for _ in 0..shift {
+                val = val.checked_mul(10)?;
+            }
+        } else if val != 0 {
+            // Past 38 digits the divisor overflows, and all digits are cut off.
+            let divisor = u32::try_from(-shift).ok().and_then(|n| 10i128.checked_pow(n));
+            let (quo, rem) = match divisor {
+                Some(divisor) => (val / divisor, val % divisor),
+                None => (0, val),
+            };
+            val = quo;
+            rest = (rem != 0) as i32;
+        }
+        Some(if negative { (-val, -rest) } else { (val, rest) })
+    }
 }
 
 impl BaseStatistics for DecimalStatistics {
@@ -99,5 +163,33 @@ impl BaseStatistics for DecimalStatistics {
         merge_min(&mut self.min, rhs.min);
         merge_max(&mut self.max, rhs.max);
         merge_sum(&mut self.sum, rhs.sum);        
+        self.bounds_unknown |= rhs.bounds_unknown;
+        if self.bounds_unknown {
+            self.min = None;
+            self.max = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let stats = DecimalStatistics::new(2);
+        assert_eq!(stats.parse("-12.05"), Some(-1205));
+        assert_eq!(stats.parse("3"), Some(300));
+        assert_eq!(stats.parse("1.5E+3"), Some(150000));
+        assert_eq!(stats.parse("125e-2"), Some(125));
+        assert_eq!(stats.parse("1.234"), None);
+        assert_eq!(stats.parse("1.2x"), None);
+        // Extra digits are rounded away from the range between the bounds.
+        assert_eq!(stats.parse_bound("-1.234", false), Some(-124));
+        assert_eq!(stats.parse_bound("-1.234", true), Some(-123));
+        assert_eq!(stats.parse_bound("1.234", false), Some(123));
+        assert_eq!(stats.parse_bound("1.234", true), Some(124));
+        assert_eq!(stats.parse_bound("-1E-50", false), Some(-1));
+        assert_eq!(stats.format(stats.parse("-0.5").unwrap()), "-0.50");
     }
 }
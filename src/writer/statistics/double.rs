@@ -7,6 +7,9 @@ pub struct DoubleStatistics {
     pub min: Option<f64>,
     pub max: Option<f64>,
     pub sum: f64,
+    /// Whether some values have no known bounds, as in statistics read without a min or max, in
+    /// which case the min and max are left out
+    pub bounds_unknown: bool,
 }
 
 fn merge_min(x: &mut Option<f64>, y: Option<f64>) {
@@ -41,12 +44,18 @@ impl DoubleStatistics {
             min: None,
             max: None,
             sum: 0.0,
+            bounds_unknown: false,
         }
     }
 
+    /// NaN values are counted as present but, following ORC Java, are excluded from the min, max 
+    /// and sum, since no ordering-based conclusion can be drawn from them.
     pub fn update(&mut self, x: f64) {
         self.num_values += 1;
         self.num_present += 1;
+        if x.is_nan() {
+            return;
+        }
         merge_min(&mut self.min, Some(x));
         merge_max(&mut self.max, Some(x));
         self.sum += x;
@@ -68,5 +77,29 @@ impl BaseStatistics for DoubleStatistics {
         merge_min(&mut self.min, rhs.min);
         merge_max(&mut self.max, rhs.max);
         self.sum += rhs.sum;
+        self.bounds_unknown |= rhs.bounds_unknown;
+        if self.bounds_unknown {
+            self.min = None;
+            self.max = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nan_excluded() {
+        let mut stats = DoubleStatistics::new();
+        stats.update(f64::NAN);
+        assert_eq!(stats.min, None);
+        stats.update(1.5);
+        stats.update(f64::NAN);
+        stats.update(f64::NEG_INFINITY);
+        assert_eq!(stats.min, Some(f64::NEG_INFINITY));
+        assert_eq!(stats.max, Some(1.5));
+        assert_eq!(stats.sum, f64::NEG_INFINITY);
+        assert_eq!(stats.num_present, 4);
     }
 }
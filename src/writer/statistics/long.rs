@@ -7,6 +7,9 @@ pub struct LongStatistics {
     pub min: Option<i64>,
     pub max: Option<i64>,
     pub sum: Option<i64>,
+    /// Whether some values have no known bounds, as in statistics read without a min or max, in
+    /// which case the min and max are left out
+    pub bounds_unknown: bool,
 }
 
 fn merge_min(x: &mut Option<i64>, y: Option<i64>) {
@@ -53,6 +56,7 @@ impl LongStatistics {
             min: None,
             max: None,
             sum: Some(0),
+            bounds_unknown: false,
         }
     }
 
@@ -80,5 +84,10 @@ impl BaseStatistics for LongStatistics {
         merge_min(&mut self.min, rhs.min);
         merge_max(&mut self.max, rhs.max);
         merge_sum(&mut self.sum, rhs.sum);        
+        self.bounds_unknown |= rhs.bounds_unknown;
+        if self.bounds_unknown {
+            self.min = None;
+            self.max = None;
+        }
     }
 }
@@ -1,34 +1,83 @@
 use super::common::BaseStatistics;
 
+/// Min/max values longer than this (in bytes) are truncated, and recorded as lower/upper bounds 
+/// rather than exact values, as in ORC Java.
+pub const MAX_BYTES_RECORDED: usize = 1024;
+
 #[derive(Debug, Clone)]
 pub struct StringStatistics {
     pub num_values: u64,
     pub num_present: u64,
     pub min: Option<String>,
     pub max: Option<String>,
+    /// Whether `min` is a truncated lower bound rather than the exact minimum
+    pub min_truncated: bool,
+    /// Whether `max` is a truncated upper bound rather than the exact maximum
+    pub max_truncated: bool,
     pub sum_lengths: u64,
+    /// Whether some values have no known bounds, as in statistics read without a min or max, in
+    /// which case the min and max are left out
+    pub bounds_unknown: bool,
+}
+
+fn truncation_point(x: &str) -> usize {
+    let mut i = MAX_BYTES_RECORDED;
+    while !x.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+/// Returns a string of at most `MAX_BYTES_RECORDED` bytes which is less than or equal to `x`.
+fn lower_bound(x: &str) -> String {
+    x[..truncation_point(x)].to_string()
 }
 
-fn merge_min(x: &mut Option<String>, y: Option<&str>) {
+/// Returns a string of at most `MAX_BYTES_RECORDED` bytes which is greater than `x`, or None if 
+/// there is no such string (i.e., if the prefix consists only of `char::MAX`).
+fn upper_bound(x: &str) -> Option<String> {
+    let mut prefix: Vec<char> = x[..truncation_point(x)].chars().collect();
+    while let Some(c) = prefix.pop() {
+        // Skip over the surrogate range, which does not correspond to valid chars.
+        let next = (c as u32 + 1..=char::MAX as u32).filter_map(std::char::from_u32).next();
+        if let Some(next) = next {
+            if next.len_utf8() <= c.len_utf8() {
+                prefix.push(next);
+                return Some(prefix.into_iter().collect());
+            }
+        }
+    }
+    None
+}
+
+fn merge_min(x: &mut Option<String>, x_truncated: &mut bool, y: Option<&str>, y_truncated: bool) {
     if let Some(yv) = y {
         if let Some(xv) = x {
-            if yv < xv {
+            if yv < xv.as_str() {
                 *x = Some(yv.to_string());
+                *x_truncated = y_truncated;
+            } else if yv == xv.as_str() {
+                *x_truncated &= y_truncated;
             }
         } else {
             *x = Some(yv.to_string());
+            *x_truncated = y_truncated;
         }
     }   
 }
 
-fn merge_max(x: &mut Option<String>, y: Option<&str>) {
+fn merge_max(x: &mut Option<String>, x_truncated: &mut bool, y: Option<&str>, y_truncated: bool) {
     if let Some(yv) = y {
         if let Some(xv) = x {
-            if yv > xv {
+            if yv > xv.as_str() {
                 *x = Some(yv.to_string());
+                *x_truncated = y_truncated;
+            } else if yv == xv.as_str() {
+                *x_truncated &= y_truncated;
             }
         } else {
             *x = Some(yv.to_string());
+            *x_truncated = y_truncated;
         }
     }   
 }
@@ -40,16 +89,33 @@ impl StringStatistics {
             num_present: 0,
             min: None,
             max: None,
+            min_truncated: false,
+            max_truncated: false,
             sum_lengths: 0,
+            bounds_unknown: false,
         }
     }
 
     pub fn update(&mut self, x: &str) {
         self.num_values += 1;
         self.num_present += 1;
-        merge_min(&mut self.min, Some(x));
-        merge_max(&mut self.max, Some(x));
         self.sum_lengths += x.len() as u64;
+        if x.len() <= MAX_BYTES_RECORDED {
+            merge_min(&mut self.min, &mut self.min_truncated, Some(x), false);
+            merge_max(&mut self.max, &mut self.max_truncated, Some(x), false);
+            return;
+        }
+        let below_min = match &self.min { Some(m) => x < m.as_str(), None => true };
+        if below_min {
+            merge_min(&mut self.min, &mut self.min_truncated, Some(&lower_bound(x)), true);
+        }
+        let above_max = match &self.max { Some(m) => x > m.as_str(), None => true };
+        if above_max {
+            match upper_bound(x) {
+                Some(ub) => merge_max(&mut self.max, &mut self.max_truncated, Some(&ub), true),
+                None => merge_max(&mut self.max, &mut self.max_truncated, Some(x), false),
+            }
+        }
     }
 }
 
@@ -65,8 +131,51 @@ impl BaseStatistics for StringStatistics {
     fn merge(&mut self, rhs: &Self) {
         self.num_values += rhs.num_values;
         self.num_present += rhs.num_present;
-        merge_min(&mut self.min, rhs.min.as_ref().map(|x| x.as_str()));
-        merge_max(&mut self.max, rhs.max.as_ref().map(|x| x.as_str()));
+        merge_min(&mut self.min, &mut self.min_truncated, rhs.min.as_deref(), rhs.min_truncated);
+        merge_max(&mut self.max, &mut self.max_truncated, rhs.max.as_deref(), rhs.max_truncated);
         self.sum_lengths += rhs.sum_lengths;
+        self.bounds_unknown |= rhs.bounds_unknown;
+        if self.bounds_unknown {
+            self.min = None;
+            self.max = None;
+            self.min_truncated = false;
+            self.max_truncated = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation() {
+        let long_a = "a".repeat(2000);
+        let long_b = format!("{}\u{e9}{}", "b".repeat(1023), "b".repeat(100));
+        let mut stats = StringStatistics::new();
+        stats.update(&long_b);
+        stats.update(&long_a);
+        assert_eq!(stats.min.as_deref(), Some(&long_a[..1024]));
+        assert!(stats.min_truncated);
+        // The multi-byte character straddling the limit is dropped, and the last one incremented.
+        assert_eq!(stats.max, Some(format!("{}c", "b".repeat(1022))));
+        assert!(stats.max_truncated);
+
+        stats.update("b");
+        assert_eq!(stats.min.as_deref(), Some(&long_a[..1024]));
+        stats.update("a");
+        assert_eq!(stats.min.as_deref(), Some("a"));
+        assert!(!stats.min_truncated);
+        stats.update("z");
+        assert_eq!(stats.max.as_deref(), Some("z"));
+        assert!(!stats.max_truncated);
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        let s = format!("{}\u{d7ff}", "x".repeat(1021));
+        assert_eq!(upper_bound(&format!("{}y", s)), Some(format!("{}\u{e000}", "x".repeat(1021))));
+        let s = "\u{10ffff}".repeat(300);
+        assert_eq!(upper_bound(&s), None);
     }
 }
@@ -6,6 +6,9 @@ pub struct TimestampStatistics {
     pub num_present: u64,
     pub min_epoch_millis: Option<i64>,
     pub max_epoch_millis: Option<i64>,
+    /// Whether some values have no known bounds, as in statistics read without a min or max, in
+    /// which case the min and max are left out
+    pub bounds_unknown: bool,
 }
 
 fn merge_min(x: &mut Option<i64>, y: Option<i64>) {
@@ -39,6 +42,7 @@ impl TimestampStatistics {
             num_present: 0,
             min_epoch_millis: None,
             max_epoch_millis: None,
+            bounds_unknown: false,
         }
     }
 
@@ -64,5 +68,10 @@ impl BaseStatistics for TimestampStatistics {
         self.num_present += rhs.num_present;
         merge_min(&mut self.min_epoch_millis, rhs.min_epoch_millis);
         merge_max(&mut self.max_epoch_millis, rhs.max_epoch_millis);
+        self.bounds_unknown |= rhs.bounds_unknown;
+        if self.bounds_unknown {
+            self.min_epoch_millis = None;
+            self.max_epoch_millis = None;
+        }
     }
 }
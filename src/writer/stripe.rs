@@ -1,4 +1,4 @@
-use std::io::{Write, Result};
+use std::io::{self, Read, Write, Result};
 use protobuf::{CodedOutputStream, RepeatedField, Message};
 
 use crate::protos::orc_proto;
@@ -8,7 +8,7 @@ use super::count_write::CountWrite;
 use super::Config;
 use super::data::{Data, BaseData};
 use super::statistics::Statistics;
-use super::compression::CompressionStream;
+use super::compression::{Compression, CompressionStream};
 
 #[derive(Debug)]
 pub struct StripeInfo {
@@ -27,11 +27,19 @@ pub struct StreamInfo {
     pub length: u64,
 }
 
+pub fn write_stripe_footer<W: Write>(out: &mut W, footer: &orc_proto::StripeFooter, compression: &Compression) -> Result<()> {
+    let mut compressed_stream = CompressionStream::new(compression);
+    let mut coded_out = CodedOutputStream::new(&mut compressed_stream);
+    footer.write_to(&mut coded_out)?;
+    coded_out.flush()?;
+    compressed_stream.finish(out)?;
+    Ok(())
+}
+
 pub struct Stripe {
     pub data: Data,
     pub offset: u64,
     pub num_rows: u64,
-    pub schema: Schema,
     pub config: Config,
 }
 
@@ -41,7 +49,6 @@ impl Stripe {
             data: Data::new(schema, config, &mut 0),
             offset: 3,
             num_rows: 0,
-            schema: schema.clone(),
             config: config.clone(),
         }
     }
@@ -53,8 +60,6 @@ impl Stripe {
     }
 
     fn write_footer<W: Write>(&mut self, out: &mut W, stream_infos: &[StreamInfo]) -> Result<()> {
-        let mut compressed_stream = CompressionStream::new(&self.config.compression);
-        let mut coded_out = CodedOutputStream::new(&mut compressed_stream);
         let mut footer = orc_proto::StripeFooter::new();
         
         let mut streams: Vec<orc_proto::Stream> = Vec::new();
@@ -70,21 +75,39 @@ impl Stripe {
         let mut encodings: Vec<orc_proto::ColumnEncoding> = Vec::new();
         self.data.column_encodings(&mut encodings);
         footer.set_columns(RepeatedField::from_vec(encodings));
-
-        footer.write_to(&mut coded_out)?;
-        coded_out.flush()?;
-        compressed_stream.finish(out)?;
-        Ok(())
+        write_stripe_footer(out, &footer, &self.config.compression)
     }
 
     pub fn finish<W: Write>(&mut self, out: &mut CountWrite<W>, stripe_infos_out: &mut Vec<StripeInfo>) -> Result<()> {
         if self.num_rows == 0 { return Ok(()) }
-        let mut stream_infos: Vec<StreamInfo> = Vec::new();
-        let mut statistics: Vec<Statistics> = Vec::new();
-        self.data.statistics(&mut statistics);
+        if let Some(block_size) = self.config.block_padding {
+            // The stripe's length is needed before it is written, to decide whether to pad. Writing
+            // it to a sink gives the length, completing the streams without discarding them, at
+            // the cost of encoding the (small) index and footer twice.
+            let stripe_info = self.write_contents(&mut CountWrite::new(io::sink()))?;
+            let len = stripe_info.index_length + stripe_info.data_length + stripe_info.footer_length;
+            let available = block_size - self.offset % block_size;
+            if len > available && len <= block_size {
+                io::copy(&mut io::repeat(0).take(available), out)?;
+                self.offset += available;
+            }
+        }
+        let mut stripe_info = self.write_contents(out)?;
+        stripe_info.offset = self.offset;
+        self.offset += stripe_info.index_length + stripe_info.data_length + stripe_info.footer_length;
+        stripe_infos_out.push(stripe_info);
+        self.num_rows = 0;
+        self.data.reset();
+        Ok(())
+    }
 
+    fn write_contents<W: Write>(&mut self, out: &mut CountWrite<W>) -> Result<StripeInfo> {
+        let mut stream_infos: Vec<StreamInfo> = Vec::new();
         let index_start_pos = out.pos();
         self.data.write_index_streams(out, &mut stream_infos)?;
+        // Read after the index is written, which completes the last row group's statistics.
+        let mut statistics: Vec<Statistics> = Vec::new();
+        self.data.statistics(&mut statistics);
 
         let data_start_pos = out.pos();
         self.data.write_data_streams(out, &mut stream_infos)?;
@@ -93,20 +116,59 @@ impl Stripe {
         self.write_footer(out, &stream_infos)?;
 
         let end_pos = out.pos();
-        let index_length = (data_start_pos - index_start_pos) as u64;
-        let data_length = (footer_start_pos - data_start_pos) as u64;
-        let footer_length = (end_pos - footer_start_pos) as u64;
-        stripe_infos_out.push(StripeInfo {
+        Ok(StripeInfo {
             offset: self.offset,
             num_rows: self.num_rows,
-            index_length,
-            data_length,
-            footer_length,
+            index_length: (data_start_pos - index_start_pos) as u64,
+            data_length: (footer_start_pos - data_start_pos) as u64,
+            footer_length: (end_pos - footer_start_pos) as u64,
             statistics,
-        });
-        self.offset += index_length + data_length + footer_length;
-        self.num_rows = 0;
-        self.data = Data::new(&self.schema, &self.config, &mut 0);
-        Ok(())
+        })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Field;
+    use crate::value::Value;
+    use crate::writer::compression::SnappyCompression;
+
+    #[test]
+    fn test_reset_between_stripes() {
+        let schema = Schema::Struct(vec![
+            Field("a".to_owned(), Schema::Long),
+            Field("b".to_owned(), Schema::List(Box::new(Schema::String))),
+            Field("c".to_owned(), Schema::Union(vec![Schema::Double, Schema::Boolean])),
+        ]);
+        let config = Config::new()
+            .with_compression(SnappyCompression::new().build())
+            .with_row_index_stride(7);
+        let mut stripe = Stripe::new(&schema, &config);
+        let mut out = CountWrite::new(Vec::new());
+        let mut stripe_infos: Vec<StripeInfo> = Vec::new();
+        for _ in 0..2 {
+            for i in 0..100 {
+                let row = Value::Struct(vec![
+                    if i % 3 == 0 { Value::Null } else { Value::Int(i) },
+                    Value::List(vec![Value::String(i.to_string()); (i % 4) as usize]),
+                    if i % 2 == 0 { Value::Union(0, Box::new(Value::Double(i as f64))) }
+                    else { Value::Union(1, Box::new(Value::Bool(true))) },
+                ]);
+                stripe.data.write_value(&row).unwrap();
+                stripe.write_batch(1).unwrap();
+            }
+            stripe.finish(&mut out, &mut stripe_infos).unwrap();
+        }
+
+        // Since both stripes contain the same rows, a correctly reset stripe is byte-for-byte identical 
+        // to the first one.
+        let bytes = out.into_inner();
+        let len = (stripe_infos[0].index_length + stripe_infos[0].data_length + stripe_infos[0].footer_length) as usize;
+        let first = &bytes[..len];
+        let second = &bytes[len..];
+        assert_eq!(first, second);
+        assert_eq!(stripe_infos[1].offset, 3 + len as u64);
+        assert_eq!(format!("{:?}", stripe_infos[0].statistics), format!("{:?}", stripe_infos[1].statistics));
+    }
+}